@@ -0,0 +1,8250 @@
+use hdk::prelude::*;
+use private_data_integrity::*;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+#[hdk_dependent_entry_types]
+enum EntryZomes {
+    IntegrityPrivateData(private_data_integrity::EntryTypes),
+}
+
+/// DNA version reported in exports and ping responses
+const DNA_VERSION: &str = "1.12";
+
+/// Liveness check - confirms the zome is callable without touching the DHT
+#[hdk_extern]
+pub fn ping(_: ()) -> ExternResult<String> {
+    Ok(format!("pong {}", DNA_VERSION))
+}
+
+/// Fail with a clear RecordDeleted error if the record has been deleted
+/// update_entry on a deleted record otherwise surfaces an opaque Holochain error
+fn ensure_not_deleted(action_hash: &ActionHash, label: &str) -> ExternResult<()> {
+    match get_details(action_hash.clone(), GetOptions::default())? {
+        Some(Details::Record(record_details)) if !record_details.deletes.is_empty() => Err(wasm_error!(
+            WasmErrorInner::Guest(format!("RecordDeleted: {} record {} has been deleted", label, action_hash))
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the record has been deleted (links and `get` can outlive the entry)
+fn is_deleted(action_hash: &ActionHash) -> ExternResult<bool> {
+    match get_details(action_hash.clone(), GetOptions::default())? {
+        Some(Details::Record(record_details)) => Ok(!record_details.deletes.is_empty()),
+        _ => Ok(false),
+    }
+}
+
+/// Follow an update chain from `hash` to its latest record
+/// Returns None if the starting record can't be found; errors if the chain breaks part-way
+fn latest_in_chain(hash: ActionHash) -> ExternResult<Option<Record>> {
+    let head = walk_update_chain(hash, details_with(GetStrategy::default()))?;
+    Ok(head.map(|(record, _)| record))
+}
+
+/// Pick the newest of several actions (e.g., updates to the same record)
+/// Actions in the same microsecond (fast password changes) tie-break on action hash so every read agrees
+fn newest_action(actions: &[SignedActionHashed]) -> Option<&SignedActionHashed> {
+    actions
+        .iter()
+        .max_by(|a, b| {
+            a.action().timestamp().cmp(&b.action().timestamp())
+                .then_with(|| a.action_address().cmp(b.action_address()))
+        })
+}
+
+/// One hop along an update chain
+enum ChainStep {
+    Next(ActionHash),   // Follow this update
+    Head(Box<Record>),  // No further updates - this is the latest record
+}
+
+/// Newest of the actions that none of `deletes` removed
+fn newest_live_action(actions: &[SignedActionHashed], deletes: &[SignedActionHashed]) -> Option<SignedActionHashed> {
+    let deleted: Vec<&ActionHash> = deletes
+        .iter()
+        .filter_map(|delete| match delete.action() {
+            Action::Delete(delete) => Some(&delete.deletes_address),
+            _ => None,
+        })
+        .collect();
+    let live: Vec<SignedActionHashed> = actions
+        .iter()
+        .filter(|action| !deleted.contains(&action.action_address()))
+        .cloned()
+        .collect();
+    newest_action(&live).cloned()
+}
+
+/// Where the chain goes from the given details
+/// Private entries can come back as entry details; their head is the newest live action that wrote the entry
+/// None when entry details have no live action left
+fn chain_step(details: Details) -> Option<ChainStep> {
+    match details {
+        Details::Record(record_details) => Some(match newest_action(&record_details.updates) {
+            Some(latest_update) => ChainStep::Next(latest_update.action_address().clone()),
+            None => ChainStep::Head(Box::new(record_details.record)),
+        }),
+        Details::Entry(entry_details) => {
+            if let Some(latest_update) = newest_action(&entry_details.updates) {
+                return Some(ChainStep::Next(latest_update.action_address().clone()));
+            }
+            let latest_live = newest_live_action(&entry_details.actions, &entry_details.deletes)?;
+            Some(ChainStep::Head(Box::new(Record::new(latest_live, Some(entry_details.entry)))))
+        }
+    }
+}
+
+/// get_details with the given strategy (e.g. Local for offline reads), as a fetch for walk_update_chain
+fn details_with(strategy: GetStrategy) -> impl Fn(&ActionHash) -> ExternResult<Option<Details>> {
+    move |hash| get_details(hash.clone(), GetOptions::default().with_strategy(strategy))
+}
+
+/// Latest record on the update chain the first of `links` points at, fetching each hop with `fetch`
+/// None when there is no link; `unresolved` is the error for a link whose record can't be fetched
+fn latest_from_first_link(
+    links: &[Link],
+    fetch: impl FnMut(&ActionHash) -> ExternResult<Option<Details>>,
+    unresolved: impl FnOnce(&ActionHash) -> WasmError,
+) -> ExternResult<Option<Record>> {
+    let Some(link) = links.first() else {
+        return Ok(None);
+    };
+    let start = ActionHash::try_from(link.target.clone())
+        .map_err(|_| wasm_error!("Invalid link target hash"))?;
+
+    match walk_update_chain(start.clone(), fetch)? {
+        Some((record, _)) => Ok(Some(record)),
+        None => Err(unresolved(&start)),
+    }
+}
+
+/// Follow an update chain from `hash` using `fetch` for each hop
+/// Returns the latest record and every action hash followed to reach it, `hash` first and the
+/// head last; None if the starting record can't be found, an error if the chain breaks part-way
+fn walk_update_chain(
+    hash: ActionHash,
+    mut fetch: impl FnMut(&ActionHash) -> ExternResult<Option<Details>>,
+) -> ExternResult<Option<(Record, Vec<ActionHash>)>> {
+    let mut current_hash = hash;
+    let mut path = Vec::new();
+
+    // Follow the ENTIRE chain, not just one level - each update may itself be updated
+    loop {
+        let step = match fetch(&current_hash)?.and_then(chain_step) {
+            Some(step) => step,
+            None if path.is_empty() => return Ok(None),
+            None => return Err(wasm_error!(
+                WasmErrorInner::Guest(format!("Record {} not found in update chain", current_hash))
+            )),
+        };
+
+        match step {
+            ChainStep::Next(next_hash) => path.push(std::mem::replace(&mut current_hash, next_hash)),
+            ChainStep::Head(record) => {
+                path.push(current_hash);
+                return Ok(Some((*record, path)));
+            }
+        }
+    }
+}
+
+// ============================================================================
+// INPUT VALIDATION - Collects every violation so clients can fix them in one pass
+// ============================================================================
+
+/// Max length for base64-encoded crypto fields (ciphertext, nonce, salt, tag)
+const MAX_ENCRYPTED_FIELD_LEN: usize = 8192;
+
+/// Max length for plaintext display fields
+const MAX_DISPLAY_NAME_LEN: usize = 256;
+
+/// Check a required base64 crypto field: non-empty, bounded length, base64 charset
+fn check_base64_field(field: &str, value: &str, violations: &mut Vec<String>) {
+    if value.is_empty() {
+        violations.push(format!("{} is required", field));
+        return;
+    }
+    if value.len() > MAX_ENCRYPTED_FIELD_LEN {
+        violations.push(format!("{} exceeds {} characters", field, MAX_ENCRYPTED_FIELD_LEN));
+    }
+    let is_base64 = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_'));
+    if !is_base64 {
+        violations.push(format!("{} is not valid base64", field));
+    }
+}
+
+/// Check that a timestamp is set (positive microseconds since epoch)
+fn check_timestamp(field: &str, value: i64, violations: &mut Vec<String>) {
+    if value <= 0 {
+        violations.push(format!("{} must be a positive timestamp", field));
+    }
+}
+
+/// Turn a list of violations into a single error listing all of them
+fn ensure_no_violations(label: &str, violations: Vec<String>) -> ExternResult<()> {
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(wasm_error!(
+        WasmErrorInner::Guest(format!("Invalid {}: {}", label, violations.join("; ")))
+    ))
+}
+
+/// Collect every problem with a UserProfile input
+/// `reject_duplicate_username` turns the username == display_name warning into a violation
+fn validate_user_profile_input(profile: &UserProfile, reject_duplicate_username: bool) -> Vec<String> {
+    let mut violations = Vec::new();
+    check_base64_field("encrypted_email", &profile.encrypted_email, &mut violations);
+    check_base64_field("nonce", &profile.nonce, &mut violations);
+    check_base64_field("salt", &profile.salt, &mut violations);
+    check_base64_field("tag", &profile.tag, &mut violations);
+    if profile.display_name.len() > MAX_DISPLAY_NAME_LEN {
+        violations.push(format!("display_name exceeds {} characters", MAX_DISPLAY_NAME_LEN));
+    }
+    // Some UIs assume username and display_name are distinct; callers choose warn or reject
+    if profile.username.as_deref() == Some(profile.display_name.as_str()) {
+        if reject_duplicate_username {
+            violations.push("username must differ from display_name".to_string());
+        } else {
+            debug!("⚠️ [PROFILE] username is identical to display_name");
+        }
+    }
+    check_timestamp("created_at", profile.created_at, &mut violations);
+    check_timestamp("updated_at", profile.updated_at, &mut violations);
+    if profile.updated_at < profile.created_at {
+        violations.push("updated_at cannot be earlier than created_at".to_string());
+    }
+    violations
+}
+
+/// Collect every problem with a RecoveryPhrase input
+fn validate_recovery_phrase_input(recovery_phrase: &RecoveryPhrase) -> Vec<String> {
+    let mut violations = Vec::new();
+    check_base64_field("encrypted_mnemonic", &recovery_phrase.encrypted_mnemonic, &mut violations);
+    check_base64_field("nonce", &recovery_phrase.nonce, &mut violations);
+    check_base64_field("salt", &recovery_phrase.salt, &mut violations);
+    check_base64_field("tag", &recovery_phrase.tag, &mut violations);
+    check_timestamp("created_at", recovery_phrase.created_at, &mut violations);
+    violations
+}
+
+/// Decoded byte lengths the client's AES-GCM / KDF scheme produces
+const SALT_LEN: u32 = 16;
+const NONCE_LEN: u32 = 12;
+const AUTH_TAG_LEN: u32 = 16;
+
+/// Result of checking one stored crypto parameter
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CryptoFieldCheck {
+    pub expected_len: u32,
+    pub decoded_len: Option<u32>,  // None if the value is neither hex nor base64
+    pub valid: bool,
+}
+
+/// Per-field validity of the profile's stored crypto parameters
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CryptoCheck {
+    pub salt: CryptoFieldCheck,
+    pub nonce: CryptoFieldCheck,
+    pub tag: CryptoFieldCheck,
+    pub all_valid: bool,
+}
+
+/// Decode base64 in any of the alphabets/padding styles check_base64_field accepts
+fn decode_base64(value: &str) -> Option<Vec<u8>> {
+    [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(value).ok())
+}
+
+/// Byte length of a hex string; None if it isn't an even-length run of hex digits
+fn hex_decoded_len(value: &str) -> Option<u32> {
+    let is_hex = !value.is_empty() && value.len() % 2 == 0 && value.chars().all(|c| c.is_ascii_hexdigit());
+    is_hex.then(|| (value.len() / 2) as u32)
+}
+
+/// Compare one crypto field's decoded length to what the scheme expects
+/// Clients store nonce and salt as hex (see README), other fields as base64
+/// Hex digits are also valid base64, so anything that reads as hex is decoded as hex
+fn check_crypto_field(value: &str, expected_len: u32) -> CryptoFieldCheck {
+    let decoded_len = hex_decoded_len(value)
+        .or_else(|| decode_base64(value).map(|bytes| bytes.len() as u32));
+    CryptoFieldCheck {
+        expected_len,
+        decoded_len,
+        valid: decoded_len == Some(expected_len),
+    }
+}
+
+/// Check that the profile's salt / nonce / tag decode to the expected byte lengths
+/// Support tool for corrupted-parameter reports; nothing is decrypted
+#[hdk_extern]
+pub fn validate_profile_crypto(_: ()) -> ExternResult<CryptoCheck> {
+    let record = get_user_profile(())?
+        .ok_or(wasm_error!("ProfileNotFound: no profile to check"))?;
+    let profile: UserProfile = record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed user profile"))?;
+    
+    let salt = check_crypto_field(&profile.salt, SALT_LEN);
+    let nonce = check_crypto_field(&profile.nonce, NONCE_LEN);
+    let tag = check_crypto_field(&profile.tag, AUTH_TAG_LEN);
+    let all_valid = salt.valid && nonce.valid && tag.valid;
+    
+    debug!("🔐 [CRYPTO] Profile crypto parameters valid: {}", all_valid);
+    Ok(CryptoCheck { salt, nonce, tag, all_valid })
+}
+
+/// Input for store_user_profile / update_user_profile
+/// The profile fields are sent flat, with the validation option alongside them
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserProfileInput {
+    #[serde(flatten)]
+    pub profile: UserProfile,
+    #[serde(default)]
+    pub reject_duplicate_username: bool,  // Error (instead of warn) when username equals display_name
+}
+
+/// Internal writes (import, password change) only warn about a duplicate username
+impl From<UserProfile> for UserProfileInput {
+    fn from(profile: UserProfile) -> Self {
+        UserProfileInput { profile, reject_duplicate_username: false }
+    }
+}
+
+/// Store encrypted user profile on private DHT
+#[hdk_extern]
+pub fn store_user_profile(input: UserProfileInput) -> ExternResult<Record> {
+    let UserProfileInput { profile, reject_duplicate_username } = input;
+    ensure_no_violations("user profile", validate_user_profile_input(&profile, reject_duplicate_username))?;
+    
+    // Create the profile entry
+    let profile_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::UserProfile(profile.clone())
+    ))?;
+    
+    // Link from agent to profile (private link)
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key.clone(),
+        profile_hash.clone(),
+        LinkTypes::AgentToProfile,
+        (),
+    )?;
+    
+    // Return the created record
+    let record = get(profile_hash, GetOptions::default())?
+        .ok_or(wasm_error!("Could not find the newly created profile"))?;
+    
+    Ok(record)
+}
+
+/// Get the current agent's encrypted profile
+/// FIXED in v1.5: Now recursively follows ENTIRE update chain (not just one level)
+/// Returns Ok(None) only when no profile link exists - a link whose record can't be
+/// resolved is a ProfileNotFound error, not "no profile"
+#[hdk_extern]
+pub fn get_user_profile(_: ()) -> ExternResult<Option<Record>> {
+    fetch_user_profile(GetStrategy::default())
+}
+
+/// Get the current agent's encrypted profile from local data only
+/// Never goes to the network, so offline clients and degraded edge nodes get a fast cache-only read
+#[hdk_extern]
+pub fn get_user_profile_local(_: ()) -> ExternResult<Option<Record>> {
+    fetch_user_profile(GetStrategy::Local)
+}
+
+/// Get the current agent's encrypted profile along with the decoded entry
+/// Saves callers from deserializing the record themselves
+#[hdk_extern]
+pub fn get_user_profile_decoded(_: ()) -> ExternResult<Option<(Record, UserProfile)>> {
+    get_user_profile(())?.map(decode_profile_record).transpose()
+}
+
+/// Pair a profile record with its decoded entry; errors if the record isn't a user profile
+fn decode_profile_record(record: Record) -> ExternResult<(Record, UserProfile)> {
+    let profile: UserProfile = record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed user profile"))?;
+
+    Ok((record, profile))
+}
+
+/// Whether the profile has been updated after the given timestamp (for client cache invalidation)
+/// Returns false if no profile exists
+#[hdk_extern]
+pub fn profile_changed_since(timestamp: i64) -> ExternResult<bool> {
+    let profile = get_user_profile_decoded(())?.map(|(_, profile)| profile);
+    Ok(profile_updated_after(profile.as_ref(), timestamp))
+}
+
+/// Whether the latest profile's updated_at is strictly after `timestamp`
+fn profile_updated_after(profile: Option<&UserProfile>, timestamp: i64) -> bool {
+    profile.is_some_and(|profile| profile.updated_at > timestamp)
+}
+
+/// Follow the AgentToProfile update chain using the given get strategy
+fn fetch_user_profile(strategy: GetStrategy) -> ExternResult<Option<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Get links from agent to profile
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToProfile)?,
+        strategy
+    )?;
+    
+    // Get the first (should only be one) profile and follow its ENTIRE update chain
+    latest_from_first_link(&links, details_with(strategy), |profile_hash| wasm_error!(
+        WasmErrorInner::Guest(format!(
+            "ProfileNotFound: profile link exists but record {} could not be resolved",
+            profile_hash
+        ))
+    ))
+}
+
+/// Update the current agent's encrypted profile
+#[hdk_extern]
+pub fn update_user_profile(input: UserProfileInput) -> ExternResult<Record> {
+    let UserProfileInput { profile, reject_duplicate_username } = input;
+    ensure_no_violations("user profile", validate_user_profile_input(&profile, reject_duplicate_username))?;
+    
+    // Get the current profile
+    let current_profile_record = get_user_profile(())?
+        .ok_or(wasm_error!("No profile found to update"))?;
+    
+    ensure_not_deleted(current_profile_record.action_address(), "Profile")?;
+    
+    // Update the entry
+    let updated_profile_hash = update_entry(
+        current_profile_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::UserProfile(profile)),
+    )?;
+    
+    // Return the updated record
+    let record = get(updated_profile_hash, GetOptions::default())?
+        .ok_or(wasm_error!("Could not find the updated profile"))?;
+
+    Ok(record)
+}
+
+/// Input for set_username
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetUsernameInput {
+    pub username: Option<String>,         // None clears the username
+    #[serde(default)]
+    pub reject_duplicate_username: bool,  // Error (instead of warn) when username equals display_name
+}
+
+/// Copy of a profile with a new username, stamped as updated at `now`
+fn with_username(mut profile: UserProfile, username: Option<String>, now: i64) -> UserProfile {
+    profile.username = username;
+    profile.updated_at = now;
+    profile
+}
+
+/// Change only the username of the current profile
+/// Goes through the same validation as update_user_profile, including the username/display_name check
+#[hdk_extern]
+pub fn set_username(input: SetUsernameInput) -> ExternResult<Record> {
+    let (_, profile) = get_user_profile_decoded(())?
+        .ok_or(wasm_error!("No profile found to update"))?;
+    
+    let profile = with_username(profile, input.username, sys_time()?.as_micros());
+    update_user_profile(UserProfileInput {
+        profile,
+        reject_duplicate_username: input.reject_duplicate_username,
+    })
+}
+
+/// Get the number of records in the profile update chain (original entry + every update)
+/// Large values mean get_user_profile has many hops to traverse
+#[hdk_extern]
+pub fn get_profile_chain_length(_: ()) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToProfile)?,
+        GetStrategy::default()
+    )?;
+
+    let Some(link) = links.first() else {
+        return Ok(0);
+    };
+
+    let profile_hash = ActionHash::try_from(link.target.clone())
+        .map_err(|_| wasm_error!("Invalid profile hash"))?;
+
+    let (_, path) = walk_update_chain(profile_hash, details_with(GetStrategy::default()))?
+        .ok_or(wasm_error!("Profile not found in chain"))?;
+
+    Ok(path.len() as u32)
+}
+
+/// Every action along the update chain starting at `hash` (empty if the start can't be found)
+fn update_chain_actions(hash: ActionHash) -> ExternResult<Vec<ActionHash>> {
+    let walked = walk_update_chain(hash, details_with(GetStrategy::default()))?;
+    Ok(walked.map(|(_, path)| path).unwrap_or_default())
+}
+
+/// Squash a long profile update chain into a single fresh entry
+/// Re-creates the latest profile and re-points AgentToProfile at it, so reads traverse one hop
+/// Every action along the superseded chain is deleted, so the old profile can't be read back as current
+#[hdk_extern]
+pub fn squash_profile(_: ()) -> ExternResult<Record> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let current_record = get_user_profile(())?
+        .ok_or(wasm_error!("No profile found to squash"))?;
+    ensure_not_deleted(current_record.action_address(), "Profile")?;
+
+    let profile: UserProfile = current_record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed user profile"))?;
+
+    // Delete ALL old links (in case there are multiple) and the chains they point at
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToProfile)?,
+        GetStrategy::default()
+    )?;
+
+    for link in links {
+        let chain_start = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid profile hash"))?;
+        for superseded in update_chain_actions(chain_start)? {
+            if !is_deleted(&superseded)? {
+                delete_entry(superseded)?;
+            }
+        }
+        delete_link(link.create_link_hash, GetOptions::default())?;
+    }
+
+    // Create a fresh entry (new chain of length 1) and link to it
+    let new_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::UserProfile(profile)
+    ))?;
+
+    create_link(
+        my_agent_pub_key,
+        new_hash.clone(),
+        LinkTypes::AgentToProfile,
+        (),
+    )?;
+
+    let record = get(new_hash, GetOptions::default())?
+        .ok_or(wasm_error!("Could not find the squashed profile"))?;
+
+    debug!("🔧 [PROFILE] Squashed profile update chain");
+    Ok(record)
+}
+
+/// Re-point AgentToProfile at a known good profile entry (e.g., after its link was lost)
+/// The record must be a live UserProfile authored by the caller; any existing profile links are replaced
+#[hdk_extern]
+pub fn relink_profile(action_hash: ActionHash) -> ExternResult<()> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let record = get(action_hash.clone(), GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest(format!("ProfileNotFound: no record at {}", action_hash))
+        ))?;
+
+    if *record.action().author() != my_agent_pub_key {
+        return Err(wasm_error!("Only the profile's author can relink it"));
+    }
+
+    record
+        .entry()
+        .to_app_option::<UserProfile>()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Record is not a user profile"))?;
+
+    ensure_not_deleted(&action_hash, "Profile")?;
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToProfile)?,
+        GetStrategy::default()
+    )?;
+
+    for link in links {
+        delete_link(link.create_link_hash, GetOptions::default())?;
+    }
+
+    create_link(
+        my_agent_pub_key,
+        action_hash,
+        LinkTypes::AgentToProfile,
+        (),
+    )?;
+
+    debug!("🔧 [PROFILE] Relinked profile");
+    Ok(())
+}
+
+/// Delete the current agent's profile (entry + AgentToProfile links)
+/// Rejected while a recovery phrase exists but is unverified - deleting then risks permanent lockout
+#[hdk_extern]
+pub fn delete_user_profile(_: ()) -> ExternResult<ActionHash> {
+    delete_profile(false)
+}
+
+/// Delete the profile even if the recovery phrase hasn't been verified yet
+/// Use when the user explicitly accepts the lockout risk
+#[hdk_extern]
+pub fn force_delete_user_profile(_: ()) -> ExternResult<ActionHash> {
+    delete_profile(true)
+}
+
+/// Reject deleting the profile while a recovery phrase exists but is unverified, unless forced
+/// `load` is only called when not forced, so a forced delete never reads the phrase
+fn ensure_recovery_phrase_verified(
+    force: bool,
+    load: impl FnOnce() -> ExternResult<Option<RecoveryPhrase>>,
+) -> ExternResult<()> {
+    if force {
+        return Ok(());
+    }
+    if load()?.is_some_and(|recovery_phrase| !recovery_phrase.verified) {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest("Recovery phrase has not been verified yet. Pass force to delete the profile anyway.".into())
+        ));
+    }
+    Ok(())
+}
+
+/// Delete the latest profile record and its links, optionally bypassing the unverified-phrase guard
+fn delete_profile(force: bool) -> ExternResult<ActionHash> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let current_record = get_user_profile(())?
+        .ok_or(wasm_error!("No profile found to delete"))?;
+
+    ensure_not_deleted(current_record.action_address(), "Profile")?;
+
+    ensure_recovery_phrase_verified(force, || {
+        Ok(get_recovery_phrase_decoded(())?.map(|(_, recovery_phrase)| recovery_phrase))
+    })?;
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToProfile)?,
+        GetStrategy::default()
+    )?;
+
+    for link in links {
+        delete_link(link.create_link_hash, GetOptions::default())?;
+    }
+
+    let delete_hash = delete_entry(current_record.action_address().clone())?;
+
+    debug!("🔧 [PROFILE] Deleted profile (force: {})", force);
+    Ok(delete_hash)
+}
+
+/// Store encrypted recovery phrase on private DHT
+#[hdk_extern]
+pub fn store_recovery_phrase(recovery_phrase: RecoveryPhrase) -> ExternResult<ActionHash> {
+    ensure_no_violations("recovery phrase", validate_recovery_phrase_input(&recovery_phrase))?;
+    
+    let recovery_phrase_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::RecoveryPhrase(recovery_phrase)
+    ))?;
+    
+    // Link from agent to recovery phrase
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key.clone(),
+        recovery_phrase_hash.clone(),
+        LinkTypes::AgentToRecoveryPhrase,
+        (),
+    )?;
+    
+    Ok(recovery_phrase_hash)
+}
+
+/// Get the current agent's encrypted recovery phrase
+/// FIXED in v1.4: Now follows the update chain to get the latest version
+/// FIXED in v1.5: Now recursively follows ENTIRE update chain (not just one level)
+#[hdk_extern]
+pub fn get_recovery_phrase(_: ()) -> ExternResult<Option<Record>> {
+    fetch_recovery_phrase(GetStrategy::default())
+}
+
+/// Get the current agent's encrypted recovery phrase from local data only
+/// Never goes to the network, so offline clients and degraded edge nodes get a fast cache-only read
+#[hdk_extern]
+pub fn get_recovery_phrase_local(_: ()) -> ExternResult<Option<Record>> {
+    fetch_recovery_phrase(GetStrategy::Local)
+}
+
+/// Get the current agent's encrypted recovery phrase along with the decoded entry
+/// Saves callers from deserializing the record themselves
+#[hdk_extern]
+pub fn get_recovery_phrase_decoded(_: ()) -> ExternResult<Option<(Record, RecoveryPhrase)>> {
+    get_recovery_phrase(())?.map(decode_recovery_phrase_record).transpose()
+}
+
+/// Pair a recovery phrase record with its decoded entry
+fn decode_recovery_phrase_record(record: Record) -> ExternResult<(Record, RecoveryPhrase)> {
+    let recovery_phrase: RecoveryPhrase = record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed recovery phrase"))?;
+
+    Ok((record, recovery_phrase))
+}
+
+/// Check whether a cached recovery phrase action hash is still the head of the update chain
+#[hdk_extern]
+pub fn is_recovery_phrase_current(action_hash: ActionHash) -> ExternResult<bool> {
+    Ok(is_chain_head(get_recovery_phrase(())?.as_ref(), &action_hash))
+}
+
+/// Whether `action_hash` is the resolved head (a superseded or unknown hash is not)
+fn is_chain_head(head: Option<&Record>, action_hash: &ActionHash) -> bool {
+    head.is_some_and(|record| record.action_address() == action_hash)
+}
+
+/// created_at of the latest recovery phrase, without shipping the ciphertext
+/// Returns None if no recovery phrase exists
+#[hdk_extern]
+pub fn get_recovery_phrase_created_at(_: ()) -> ExternResult<Option<i64>> {
+    recovery_phrase_created_at(get_recovery_phrase(())?)
+}
+
+/// created_at decoded from the latest recovery phrase record, if there is one
+fn recovery_phrase_created_at(record: Option<Record>) -> ExternResult<Option<i64>> {
+    let created_at = record
+        .map(decode_recovery_phrase_record)
+        .transpose()?
+        .map(|(_, recovery_phrase)| recovery_phrase.created_at);
+
+    Ok(created_at)
+}
+
+/// Get the recovery phrase, deleting stale links if more than one exists
+/// Keeps the link whose chain head has the newest created_at, so later reads resolve a single link
+#[hdk_extern]
+pub fn get_recovery_phrase_and_compact(_: ()) -> ExternResult<Option<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToRecoveryPhrase)?,
+        GetStrategy::default()
+    )?;
+    
+    if links.len() <= 1 {
+        return get_recovery_phrase(());
+    }
+    
+    let Some((newest, stale_links)) = newest_recovery_phrase(links, details_with(GetStrategy::default()))? else {
+        return Ok(None);
+    };
+    
+    for link_hash in &stale_links {
+        delete_link(link_hash.clone(), GetOptions::default())?;
+    }
+    
+    debug!("🔐 [RECOVERY] Removed {} stale recovery phrase links", stale_links.len());
+    Ok(Some(newest))
+}
+
+/// Newest recovery phrase head behind `links` (by created_at, ties to the higher action hash)
+/// and the create-link hashes of every other link
+fn newest_recovery_phrase(
+    links: Vec<Link>,
+    mut fetch: impl FnMut(&ActionHash) -> ExternResult<Option<Details>>,
+) -> ExternResult<Option<(Record, Vec<ActionHash>)>> {
+    let mut heads = Vec::new();
+    for link in links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid recovery phrase hash"))?;
+        
+        if let Some((record, _)) = walk_update_chain(hash, &mut fetch)? {
+            if let Some(recovery_phrase) = record.entry().to_app_option::<RecoveryPhrase>().ok().flatten() {
+                heads.push((link, record, recovery_phrase.created_at));
+            }
+        }
+    }
+    
+    // Newest first - keep index 0, delete the rest
+    heads.sort_by(|(_, a_record, a), (_, b_record, b)| {
+        b.cmp(a).then_with(|| b_record.action_address().cmp(a_record.action_address()))
+    });
+    
+    let mut heads = heads.into_iter();
+    let Some((_, newest, _)) = heads.next() else {
+        return Ok(None);
+    };
+    
+    Ok(Some((newest, heads.map(|(link, _, _)| link.create_link_hash).collect())))
+}
+
+/// Follow the AgentToRecoveryPhrase update chain using the given get strategy
+fn fetch_recovery_phrase(strategy: GetStrategy) -> ExternResult<Option<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Get links from agent to recovery phrase
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToRecoveryPhrase)?,
+        strategy
+    )?;
+    
+    // Get the first (should only be one) recovery phrase and follow its ENTIRE update chain
+    // This is CRITICAL - when update_recovery_phrase is called multiple times  
+    // (e.g., during repeated password changes), each creates a new update.
+    latest_from_first_link(&links, details_with(strategy), |_| wasm_error!("Recovery phrase not found in chain"))
+}
+
+/// Mark recovery phrase as verified
+#[hdk_extern]
+pub fn mark_recovery_phrase_verified(_: ()) -> ExternResult<ActionHash> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Get current recovery phrase
+    let current_record = get_recovery_phrase(())?
+        .ok_or(wasm_error!("No recovery phrase found"))?;
+    
+    let mut recovery_phrase: RecoveryPhrase = current_record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed recovery phrase"))?;
+    
+    // Mark as verified AND update timestamp to NOW
+    // CRITICAL: We must update created_at so the new entry has a newer timestamp than the old one!
+    let old_timestamp = recovery_phrase.created_at;
+    let old_verified = recovery_phrase.verified;
+    
+    recovery_phrase.verified = true;
+    recovery_phrase.created_at = sys_time()?.as_micros();
+    
+    hdk::prelude::debug!("🔧 [VERIFY] OLD: verified={}, created_at={}", old_verified, old_timestamp);
+    hdk::prelude::debug!("🔧 [VERIFY] NEW: verified={}, created_at={}", recovery_phrase.verified, recovery_phrase.created_at);
+    
+    // Delete ALL old links (in case there are multiple)
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToRecoveryPhrase)?,
+        GetStrategy::default()
+    )?;
+    
+    for link in links {
+        delete_link(link.create_link_hash, GetOptions::default())?;
+    }
+    
+    // Create NEW recovery phrase entry (don't use update_entry - it's unreliable with links)
+    let new_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::RecoveryPhrase(recovery_phrase)
+    ))?;
+    
+    // Create new link pointing to the new entry
+    create_link(
+        my_agent_pub_key,
+        new_hash.clone(),
+        LinkTypes::AgentToRecoveryPhrase,
+        (),
+    )?;
+    
+    // NOTE: We don't delete the old entry because delete_entry() only marks it as deleted,
+    // not removes it, which can cause confusion. Instead, we rely on timestamp sorting in
+    // get_recovery_phrase() to always return the most recent entry.
+    
+    Ok(new_hash)
+}
+
+/// Update the current agent's encrypted recovery phrase
+/// ADDED in v1.4: This function was missing, causing password changes to fail
+/// Rejected while the current phrase is unverified (use force_update_recovery_phrase to override)
+#[hdk_extern]
+pub fn update_recovery_phrase(recovery_phrase: RecoveryPhrase) -> ExternResult<Record> {
+    write_recovery_phrase_update(recovery_phrase, false)
+}
+
+/// Update the recovery phrase even if the current one hasn't been verified yet
+/// Use when the user explicitly accepts overwriting a phrase they may not have saved
+#[hdk_extern]
+pub fn force_update_recovery_phrase(recovery_phrase: RecoveryPhrase) -> ExternResult<Record> {
+    write_recovery_phrase_update(recovery_phrase, true)
+}
+
+/// Update the recovery phrase chain, optionally bypassing the unverified-phrase guard
+fn write_recovery_phrase_update(recovery_phrase: RecoveryPhrase, force: bool) -> ExternResult<Record> {
+    ensure_no_violations("recovery phrase", validate_recovery_phrase_input(&recovery_phrase))?;
+    
+    // Get the current recovery phrase
+    let current_record = get_recovery_phrase(())?
+        .ok_or(wasm_error!("No recovery phrase found to update"))?;
+    
+    ensure_not_deleted(current_record.action_address(), "Recovery phrase")?;
+    
+    // Don't overwrite a phrase the user may never have saved
+    if !force {
+        let current: RecoveryPhrase = current_record
+            .entry()
+            .to_app_option()
+            .map_err(|e| wasm_error!(e))?
+            .ok_or(wasm_error!("Malformed recovery phrase"))?;
+        
+        if !current.verified {
+            return Err(wasm_error!(
+                WasmErrorInner::Guest("Recovery phrase has not been verified yet. Pass force to overwrite it.".into())
+            ));
+        }
+    }
+    
+    // Update the entry using Holochain's update mechanism
+    // This creates a new entry and adds it to the update chain
+    let updated_hash = update_entry(
+        current_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::RecoveryPhrase(recovery_phrase)),
+    )?;
+    
+    // Return the updated record
+    let record = get(updated_hash, GetOptions::default())?
+        .ok_or(wasm_error!("Could not find the updated recovery phrase"))?;
+    
+    Ok(record)
+}
+
+/// Everything the login flow needs in one round-trip
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginBundle {
+    pub profile: Option<Record>,                 // Latest profile (to decrypt email)
+    pub recovery_phrase_verified: Option<bool>,  // None if no recovery phrase exists
+}
+
+/// Get the latest profile and the recovery phrase's verified status in one zome call
+#[hdk_extern]
+pub fn get_login_bundle(_: ()) -> ExternResult<LoginBundle> {
+    login_bundle(get_user_profile(())?, get_recovery_phrase(())?)
+}
+
+/// Login bundle from the latest profile and recovery phrase records
+fn login_bundle(profile: Option<Record>, recovery_phrase: Option<Record>) -> ExternResult<LoginBundle> {
+    let recovery_phrase_verified = recovery_phrase
+        .map(decode_recovery_phrase_record)
+        .transpose()?
+        .map(|(_, recovery_phrase)| recovery_phrase.verified);
+    
+    Ok(LoginBundle {
+        profile,
+        recovery_phrase_verified,
+    })
+}
+
+/// Input for re-encrypting all password-protected data after a password change
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReencryptAllInput {
+    pub new_profile: UserProfile,
+    pub new_recovery: RecoveryPhrase,
+}
+
+/// Re-encrypt profile and recovery phrase with new crypto material (salt/nonce/tag)
+/// Both updates happen in one zome call, so they commit together or not at all
+/// The mnemonic itself is unchanged, so the unverified-phrase guard doesn't apply
+#[hdk_extern]
+pub fn reencrypt_all(input: ReencryptAllInput) -> ExternResult<()> {
+    let has_profile = get_user_profile(())?.is_some();
+    let has_recovery = get_recovery_phrase(())?.is_some();
+    
+    apply_reencryption(
+        input,
+        has_profile,
+        has_recovery,
+        |profile| update_user_profile(profile.into()),
+        |recovery| write_recovery_phrase_update(recovery, true),
+    )?;
+    
+    debug!("🔐 [REENCRYPT] Profile and recovery phrase re-encrypted");
+    Ok(())
+}
+
+/// Check both records exist and both inputs are valid, then write the two updates
+/// Nothing is written unless both writes can go ahead
+fn apply_reencryption(
+    input: ReencryptAllInput,
+    has_profile: bool,
+    has_recovery: bool,
+    write_profile: impl FnOnce(UserProfile) -> ExternResult<Record>,
+    write_recovery: impl FnOnce(RecoveryPhrase) -> ExternResult<Record>,
+) -> ExternResult<()> {
+    if !has_profile {
+        return Err(wasm_error!("No profile found to re-encrypt"));
+    }
+    if !has_recovery {
+        return Err(wasm_error!("No recovery phrase found to re-encrypt"));
+    }
+    ensure_no_violations("user profile", validate_user_profile_input(&input.new_profile, false))?;
+    ensure_no_violations("recovery phrase", validate_recovery_phrase_input(&input.new_recovery))?;
+    
+    write_profile(input.new_profile)?;
+    write_recovery(input.new_recovery)?;
+    Ok(())
+}
+
+/// Max length for a client-supplied idempotency key (it lives in the link tag)
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+
+/// Separates a link tag's own payload from an appended idempotency key
+/// Can't occur inside a timestamp tag or app_id tag, so the payload is always recoverable
+const IDEMPOTENCY_MARKER: &[u8] = b"\0idempotency:";
+
+/// Link tag with an idempotency key appended to the given payload (payload alone if no key)
+fn tag_with_idempotency_key(mut payload: Vec<u8>, idempotency_key: Option<&str>) -> LinkTag {
+    if let Some(key) = idempotency_key {
+        payload.extend_from_slice(IDEMPOTENCY_MARKER);
+        payload.extend_from_slice(key.as_bytes());
+    }
+    LinkTag::from(payload)
+}
+
+/// A link tag's payload with any idempotency key removed
+fn tag_payload(tag: &LinkTag) -> &[u8] {
+    let bytes = tag.0.as_slice();
+    match bytes.windows(IDEMPOTENCY_MARKER.len()).position(|window| window == IDEMPOTENCY_MARKER) {
+        Some(position) => &bytes[..position],
+        None => bytes,
+    }
+}
+
+/// Target of an existing link stored with this idempotency key, if the store already happened
+/// Rejects keys too long to fit comfortably in a link tag
+fn find_idempotent_store(link_type: LinkTypes, idempotency_key: Option<&str>) -> ExternResult<Option<ActionHash>> {
+    let Some(key) = idempotency_key else {
+        return Ok(None);
+    };
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "idempotency_key must be 1-{} bytes", MAX_IDEMPOTENCY_KEY_LEN
+        ))));
+    }
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, link_type)?,
+        GetStrategy::default()
+    )?;
+    
+    let suffix = [IDEMPOTENCY_MARKER, key.as_bytes()].concat();
+    let existing = links
+        .into_iter()
+        .find(|link| link.tag.0.ends_with(&suffix))
+        .and_then(|link| ActionHash::try_from(link.target).ok());
+    
+    Ok(existing)
+}
+
+/// Input wrappers for the store_* externs that accept a client retry key
+/// The entry's fields are flattened, so clients send the entry object with an extra idempotency_key;
+/// the key goes into the link tag and never into the entry itself
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StoreSessionInput {
+    #[serde(flatten)]
+    pub session: Session,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StoreLoginActivityInput {
+    #[serde(flatten)]
+    pub activity: LoginActivity,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StoreDashboardActivityInput {
+    #[serde(flatten)]
+    pub activity: DashboardActivity,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StoreOAuthActivityInput {
+    #[serde(flatten)]
+    pub activity: OAuthActivity,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// Internal writes (session migration) have no retry key
+/// Imports bypass the store_* functions entirely (see create_session and create_*_activity)
+impl From<LoginActivity> for StoreLoginActivityInput {
+    fn from(activity: LoginActivity) -> Self {
+        StoreLoginActivityInput { activity, idempotency_key: None }
+    }
+}
+
+/// Store a session on private DHT
+/// Evicts the least recently active sessions if the max_sessions cap is exceeded
+/// A repeat store with the same idempotency_key is a no-op returning the original hash
+#[hdk_extern]
+pub fn store_session(input: StoreSessionInput) -> ExternResult<ActionHash> {
+    let StoreSessionInput { session, idempotency_key } = input;
+    if let Some(existing_hash) = find_idempotent_store(LinkTypes::AgentToSessions, idempotency_key.as_deref())? {
+        debug!("📱 [SESSIONS] Session already stored for this idempotency key");
+        return Ok(existing_hash);
+    }
+    
+    let session_hash = create_session(session, idempotency_key.as_deref())?;
+    
+    // Evict only once the new session is written, so a failed create loses nothing
+    let max_sessions = newest_privacy_settings()?
+        .map(|(_, settings)| settings.max_sessions)
+        .unwrap_or_else(default_max_sessions);
+    evict_oldest_sessions(max_sessions.saturating_sub(1) as usize, &session_hash)?;
+    
+    Ok(session_hash)
+}
+
+/// Create a session entry and its agent link, without idempotency or eviction
+/// Used directly by import so restoring a bundle never deletes imported sessions
+fn create_session(session: Session, idempotency_key: Option<&str>) -> ExternResult<ActionHash> {
+    let session_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::Session(session)
+    ))?;
+    
+    // Link from agent to session
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key,
+        session_hash.clone(),
+        LinkTypes::AgentToSessions,
+        tag_with_idempotency_key(Vec::new(), idempotency_key),
+    )?;
+    
+    Ok(session_hash)
+}
+
+/// The least recently active sessions beyond the `keep` most recent, oldest first
+fn sessions_to_evict(mut sessions: Vec<SessionHead>, keep: usize) -> Vec<SessionHead> {
+    if sessions.len() <= keep {
+        return Vec::new();
+    }
+    
+    sessions.sort_by_key(|head| head.session.last_active);
+    let evict_count = sessions.len() - keep;
+    sessions.truncate(evict_count);
+    sessions
+}
+
+/// Delete the least recently active sessions (entry + link) until at most `keep` others remain
+/// `new_session_hash` is the session just stored; it is never evicted
+fn evict_oldest_sessions(keep: usize, new_session_hash: &ActionHash) -> ExternResult<u32> {
+    let others: Vec<SessionHead> = session_heads()?
+        .into_iter()
+        .filter(|head| head.original_hash != *new_session_hash)
+        .collect();
+    
+    let evicted = sessions_to_evict(others, keep);
+    for head in &evicted {
+        delete_entry(head.original_hash.clone())?;
+        delete_link(head.link_hash.clone(), GetOptions::default())?;
+    }
+    
+    debug!("🧹 [SESSIONS] Evicted {} oldest sessions", evicted.len());
+    Ok(evicted.len() as u32)
+}
+
+/// Get all sessions for the current agent
+#[hdk_extern]
+pub fn get_my_sessions(_: ()) -> ExternResult<Vec<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Get links from agent to sessions
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToSessions)?,
+        GetStrategy::default()
+    )?;
+    
+    // Get all session records
+    let mut sessions = Vec::new();
+    for link in links {
+        let session_hash = ActionHash::try_from(link.target)
+            .map_err(|_| wasm_error!("Invalid session hash"))?;
+        
+        // Latest version so touch_session / set_current_session changes are visible
+        if let Some(record) = latest_in_chain(session_hash)? {
+            sessions.push(record);
+        }
+    }
+    
+    Ok(sessions)
+}
+
+/// Get all sessions sorted by last_active, most recent first (for the "active devices" UI)
+#[hdk_extern]
+pub fn get_active_sessions(_: ()) -> ExternResult<Vec<Record>> {
+    Ok(most_recently_active_first(get_my_sessions(())?))
+}
+
+/// Session records ordered by last_active, most recent first (records without a session are dropped)
+fn most_recently_active_first(records: Vec<Record>) -> Vec<Record> {
+    let mut sessions: Vec<(i64, Record)> = records
+        .into_iter()
+        .filter_map(|record| {
+            let session = record.entry().to_app_option::<Session>().ok().flatten()?;
+            Some((session.last_active, record))
+        })
+        .collect();
+    
+    sessions.sort_by_key(|(last_active, _)| std::cmp::Reverse(*last_active));
+    sessions.into_iter().map(|(_, record)| record).collect()
+}
+
+/// Number of distinct devices across the agent's sessions ("you've logged in from N devices")
+/// Devices are keyed by trimmed, lowercased device_info; sessions without device info are ignored
+#[hdk_extern]
+pub fn count_unique_devices(_: ()) -> ExternResult<u32> {
+    let devices: std::collections::HashSet<String> = get_my_sessions(())?
+        .iter()
+        .filter_map(|record| record.entry().to_app_option::<Session>().ok().flatten())
+        .map(|session| session_device_key(&session))
+        .filter(|device_key| !device_key.is_empty())
+        .collect();
+    
+    Ok(devices.len() as u32)
+}
+
+/// Normalized device fingerprint for a session (device_info, trimmed and lowercased)
+fn session_device_key(session: &Session) -> String {
+    session.device_info.trim().to_lowercase()
+}
+
+/// A session plus whether it belongs to the device making the request
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnotatedSession {
+    pub session: Session,
+    pub is_current: bool,
+}
+
+/// Get all sessions, flagging those whose device matches the caller's fingerprint
+/// Lets the sessions list render a "this device" marker without a set_current_session write
+/// Fingerprints are compared the same way count_unique_devices groups devices
+#[hdk_extern]
+pub fn get_my_sessions_annotated(current_fingerprint: String) -> ExternResult<Vec<AnnotatedSession>> {
+    let current_key = current_fingerprint.trim().to_lowercase();
+    
+    let sessions = get_my_sessions(())?
+        .iter()
+        .filter_map(|record| record.entry().to_app_option::<Session>().ok().flatten())
+        .map(|session| AnnotatedSession {
+            is_current: !current_key.is_empty() && session_device_key(&session) == current_key,
+            session,
+        })
+        .collect();
+    
+    Ok(sessions)
+}
+
+/// Pairs of sessions from different IPs whose last_active times are within `within_seconds`
+/// of each other - a signal that the account may be in use from two places at once
+#[hdk_extern]
+pub fn detect_concurrent_sessions(within_seconds: i64) -> ExternResult<Vec<(Record, Record)>> {
+    let window_micros = within_seconds.saturating_mul(1_000_000);
+    
+    let sessions: Vec<(Session, Record)> = get_my_sessions(())?
+        .into_iter()
+        .filter_map(|record| {
+            let session = record.entry().to_app_option::<Session>().ok().flatten()?;
+            Some((session, record))
+        })
+        .collect();
+    
+    let mut pairs = Vec::new();
+    for (index, (session, record)) in sessions.iter().enumerate() {
+        for (other_session, other_record) in &sessions[index + 1..] {
+            let overlapping = (session.last_active - other_session.last_active).abs() <= window_micros;
+            if overlapping && session.ip_address != other_session.ip_address {
+                pairs.push((record.clone(), other_record.clone()));
+            }
+        }
+    }
+    
+    debug!("🔐 [SESSIONS] Found {} concurrent session pairs", pairs.len());
+    Ok(pairs)
+}
+
+/// Bump a session's last_active to now, updating from the head of its chain
+/// Integrity validation rejects the update if last_active would precede created_at
+#[hdk_extern]
+pub fn touch_session(session_hash: ActionHash) -> ExternResult<ActionHash> {
+    let record = latest_in_chain(session_hash.clone())?
+        .ok_or(wasm_error!("Session not found"))?;
+    
+    let mut session: Session = record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed session"))?;
+    
+    session.last_active = sys_time()?.as_micros();
+    
+    update_entry(
+        record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::Session(session)),
+    )
+}
+
+/// A live session: the create its AgentToSessions link points at, plus the head of its update chain
+struct SessionHead {
+    original_hash: ActionHash,
+    head_hash: ActionHash,
+    link_hash: ActionHash,
+    session: Session,
+}
+
+impl SessionHead {
+    /// Clients may hold either hash: links point at the create, getters return the head
+    fn is_named_by(&self, session_hash: &ActionHash) -> bool {
+        self.original_hash == *session_hash || self.head_hash == *session_hash
+    }
+}
+
+/// Every linked session that hasn't been deleted, read from the head of its chain
+fn session_heads() -> ExternResult<Vec<SessionHead>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToSessions)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut sessions = Vec::new();
+    for link in links {
+        let original_hash = ActionHash::try_from(link.target)
+            .map_err(|_| wasm_error!("Invalid session hash"))?;
+        
+        if is_deleted(&original_hash)? {
+            continue;
+        }
+        if let Some(head) = latest_in_chain(original_hash.clone())? {
+            if let Some(session) = head.entry().to_app_option::<Session>().ok().flatten() {
+                sessions.push(SessionHead {
+                    original_hash,
+                    head_hash: head.action_address().clone(),
+                    link_hash: link.create_link_hash,
+                    session,
+                });
+            }
+        }
+    }
+    
+    Ok(sessions)
+}
+
+/// Flag the named session as current and every other one as not current
+/// Returns the indexes of the sessions whose flag changed; errors if no session matches
+fn mark_current_session(sessions: &mut [SessionHead], session_hash: &ActionHash) -> ExternResult<Vec<usize>> {
+    if !sessions.iter().any(|head| head.is_named_by(session_hash)) {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest(format!("Session not found: {}", session_hash))
+        ));
+    }
+    
+    let mut changed = Vec::new();
+    for (index, head) in sessions.iter_mut().enumerate() {
+        let is_current = head.is_named_by(session_hash);
+        if head.session.is_current != is_current {
+            head.session.is_current = is_current;
+            changed.push(index);
+        }
+    }
+    Ok(changed)
+}
+
+/// Mark one session as the device currently in use and clear the flag on all others
+/// Accepts the original session hash or the head hash returned by get_my_sessions
+/// Only sessions whose flag actually changes are updated
+#[hdk_extern]
+pub fn set_current_session(session_hash: ActionHash) -> ExternResult<()> {
+    let mut sessions = session_heads()?;
+    let changed = mark_current_session(&mut sessions, &session_hash)?;
+    
+    // Update from the head so earlier touches/flag changes stay in the chain
+    for index in changed {
+        let head = &sessions[index];
+        update_entry(
+            head.head_hash.clone(),
+            &EntryZomes::IntegrityPrivateData(EntryTypes::Session(head.session.clone())),
+        )?;
+    }
+    
+    debug!("📱 [SESSIONS] Marked session {} as current", session_hash);
+    Ok(())
+}
+
+/// Delete a specific session
+#[hdk_extern]
+pub fn delete_session(session_hash: ActionHash) -> ExternResult<ActionHash> {
+    delete_entry(session_hash)
+}
+
+/// Create-link hashes of the links (target, create_link_hash) pointing at any of the given targets
+fn link_hashes_targeting(links: &[(AnyLinkableHash, ActionHash)], targets: &[ActionHash]) -> Vec<ActionHash> {
+    links
+        .iter()
+        .filter(|(target, _)| targets.iter().any(|hash| AnyLinkableHash::from(hash.clone()) == *target))
+        .map(|(_, create_link_hash)| create_link_hash.clone())
+        .collect()
+}
+
+/// Walk back from any hash in an update chain to the create it started from
+/// `updated_from` returns the action an update replaced, or None once the hash names a create
+fn resolve_original(hash: ActionHash, mut updated_from: impl FnMut(&ActionHash) -> ExternResult<Option<ActionHash>>) -> ExternResult<ActionHash> {
+    let mut current_hash = hash;
+    while let Some(previous_hash) = updated_from(&current_hash)? {
+        current_hash = previous_hash;
+    }
+    Ok(current_hash)
+}
+
+/// The create action behind a (possibly updated) record; unknown hashes are returned unchanged
+fn original_action_hash(hash: ActionHash) -> ExternResult<ActionHash> {
+    resolve_original(hash, |current_hash| {
+        Ok(get(current_hash.clone(), GetOptions::default())?.and_then(|record| match record.action() {
+            Action::Update(update) => Some(update.original_action_address.clone()),
+            _ => None,
+        }))
+    })
+}
+
+/// Delete several sessions (and their AgentToSessions links) in one call
+/// Accepts original or head hashes (as returned by get_my_sessions); both delete the original create
+/// Missing or already-deleted sessions are skipped; returns the hashes of the delete actions made
+#[hdk_extern]
+pub fn delete_sessions(session_hashes: Vec<ActionHash>) -> ExternResult<Vec<ActionHash>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let links: Vec<(AnyLinkableHash, ActionHash)> = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToSessions)?,
+        GetStrategy::default()
+    )?
+        .into_iter()
+        .map(|link| (link.target, link.create_link_hash))
+        .collect();
+    
+    // Links point at the create, so resolve heads back to it (once per session)
+    let mut original_hashes = Vec::new();
+    for session_hash in session_hashes {
+        let original_hash = original_action_hash(session_hash)?;
+        if !original_hashes.contains(&original_hash) {
+            original_hashes.push(original_hash);
+        }
+    }
+    
+    let mut delete_hashes = Vec::new();
+    
+    for session_hash in &original_hashes {
+        match get_details(session_hash.clone(), GetOptions::default())? {
+            Some(Details::Record(record_details)) if record_details.deletes.is_empty() => {
+                delete_hashes.push(delete_entry(session_hash.clone())?);
+            }
+            _ => debug!("🧹 [SESSIONS] Skipping missing or already deleted session {}", session_hash),
+        }
+    }
+    
+    // Drop the links too, including any left pointing at sessions deleted earlier
+    for link_hash in link_hashes_targeting(&links, &original_hashes) {
+        delete_link(link_hash, GetOptions::default())?;
+    }
+    
+    debug!("🧹 [SESSIONS] Deleted {} sessions", delete_hashes.len());
+    Ok(delete_hashes)
+}
+
+/// Delete every session and its link ("log out of all devices")
+/// Returns the number of sessions deleted
+#[hdk_extern]
+pub fn delete_all_sessions(_: ()) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToSessions)?,
+        GetStrategy::default()
+    )?;
+
+    let (session_hashes, link_hashes) = sessions_to_clear(links, details_with(GetStrategy::default()))?;
+    for session_hash in &session_hashes {
+        delete_entry(session_hash.clone())?;
+    }
+    for link_hash in link_hashes {
+        delete_link(link_hash, GetOptions::default())?;
+    }
+
+    debug!("🧹 [SESSIONS] Deleted {} sessions", session_hashes.len());
+    Ok(session_hashes.len() as u32)
+}
+
+/// Sessions still live behind the links, and every link to delete
+/// Links can outlive their session; only sessions not yet deleted are returned, so only they are counted
+fn sessions_to_clear(
+    links: Vec<Link>,
+    mut fetch: impl FnMut(&ActionHash) -> ExternResult<Option<Details>>,
+) -> ExternResult<(Vec<ActionHash>, Vec<ActionHash>)> {
+    let mut session_hashes = Vec::new();
+    let mut link_hashes = Vec::new();
+
+    for link in links {
+        let session_hash = ActionHash::try_from(link.target)
+            .map_err(|_| wasm_error!("Invalid session hash"))?;
+
+        if let Some(Details::Record(record_details)) = fetch(&session_hash)? {
+            if record_details.deletes.is_empty() {
+                session_hashes.push(session_hash);
+            }
+        }
+        link_hashes.push(link.create_link_hash);
+    }
+
+    Ok((session_hashes, link_hashes))
+}
+
+/// Get all sessions created on a specific edge node
+#[hdk_extern]
+pub fn get_sessions_by_conductor(conductor_id: String) -> ExternResult<Vec<Record>> {
+    Ok(session_records_on_conductor(get_my_sessions(())?, &conductor_id))
+}
+
+/// Session records created on the given edge node
+fn session_records_on_conductor(records: Vec<Record>, conductor_id: &str) -> Vec<Record> {
+    records
+        .into_iter()
+        .filter(|record| {
+            record
+                .entry()
+                .to_app_option::<Session>()
+                .ok()
+                .flatten()
+                .is_some_and(|session| session.conductor_id == conductor_id)
+        })
+        .collect()
+}
+
+/// Live session heads created on the given edge node
+fn session_heads_on_conductor(heads: Vec<SessionHead>, conductor_id: &str) -> Vec<SessionHead> {
+    heads.into_iter().filter(|head| head.session.conductor_id == conductor_id).collect()
+}
+
+/// Delete all sessions created on a specific edge node (e.g., a decommissioned conductor)
+/// Returns the number of sessions deleted
+#[hdk_extern]
+pub fn delete_sessions_by_conductor(conductor_id: String) -> ExternResult<u32> {
+    // session_heads skips sessions already deleted, so they aren't re-deleted or counted
+    let mut deleted_count = 0;
+
+    for head in session_heads_on_conductor(session_heads()?, &conductor_id) {
+        delete_entry(head.original_hash)?;
+        delete_link(head.link_hash, GetOptions::default())?;
+        deleted_count += 1;
+    }
+
+    debug!("🧹 [SESSIONS] Deleted {} sessions on conductor {}", deleted_count, conductor_id);
+    Ok(deleted_count)
+}
+
+/// Login method recorded on activities converted from legacy sessions
+const LEGACY_SESSION_LOGIN_METHOD: &str = "legacy_session";
+
+/// LoginActivity equivalent of a legacy session; session_id is the session's original hash
+fn legacy_login_activity(session_hash: &ActionHash, session: &Session) -> LoginActivity {
+    let non_empty = |value: &str| if value.is_empty() { None } else { Some(value.to_string()) };
+    LoginActivity {
+        timestamp: session.created_at,
+        login_method: LEGACY_SESSION_LOGIN_METHOD.to_string(),
+        ip_address: non_empty(&session.ip_address),
+        user_agent: non_empty(&session.user_agent),
+        session_id: session_hash.to_string(),
+        created_at: session.created_at,
+        seq: None,
+        region: None,
+    }
+}
+
+/// Whether a session was already converted by an earlier migration run
+fn is_migrated_session(session_hash: &ActionHash, login_activities: &[LoginActivity]) -> bool {
+    let session_id = session_hash.to_string();
+    login_activities.iter().any(|activity| {
+        activity.login_method == LEGACY_SESSION_LOGIN_METHOD && activity.session_id == session_id
+    })
+}
+
+/// Convert legacy Session entries into LoginActivity (sessions are deprecated)
+/// Takes delete_migrated rather than `()` so deleting stays optional: pass true to delete each
+/// session (entry + link) once converted. Safe to re-run - sessions converted by an earlier run are
+/// not converted again (but are deleted if asked). Returns the number newly migrated
+#[hdk_extern]
+pub fn migrate_sessions_to_login_activity(delete_migrated: bool) -> ExternResult<u32> {
+    let login_activities: Vec<LoginActivity> = login_activity_heads()?
+        .into_iter()
+        .map(|(_, activity)| activity)
+        .collect();
+
+    let mut migrated_count = 0;
+
+    for head in session_heads()? {
+        // Written directly so tracking toggles and login dedupe can't drop converted sessions
+        if !is_migrated_session(&head.original_hash, &login_activities) {
+            create_login_activity(legacy_login_activity(&head.original_hash, &head.session), None)?;
+            migrated_count += 1;
+        }
+
+        if delete_migrated {
+            delete_entry(head.original_hash)?;
+            delete_link(head.link_hash, GetOptions::default())?;
+        }
+    }
+
+    debug!("🧹 [SESSIONS] Migrated {} sessions to login activity", migrated_count);
+    Ok(migrated_count)
+}
+
+// ============================================================================
+// DNA MIGRATION SUPPORT - Export/Import Functions (v1.0)
+// ============================================================================
+
+/// Exported data bundle for migration
+/// Collections added after v1.0 default to empty so older bundles (e.g., v1.0) still deserialize
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedData {
+    // v1.5 data (backward compatibility)
+    pub user_profile: Option<UserProfile>,
+    pub recovery_phrase: Option<RecoveryPhrase>,
+    pub sessions: Vec<Session>,
+    #[serde(default)]
+    pub email_permissions: Vec<EmailPermission>,  // ✅ CRITICAL: Was missing in v1.5!
+    
+    // v1.6 data (new, will be empty on v1.5 export)
+    #[serde(default)]
+    pub login_activities: Vec<LoginActivity>,
+    #[serde(default)]
+    pub dashboard_activities: Vec<DashboardActivity>,
+    #[serde(default)]
+    pub oauth_activities: Vec<OAuthActivity>,
+    pub privacy_settings: Option<PrivacySettings>,
+    
+    // v1.9 data (new, will be empty on v1.8 export)
+    #[serde(default)]
+    pub analytics_ids: Vec<AppAnalyticsId>,  // ✅ NEW v1.9: Zero-knowledge analytics
+    
+    // v1.10 data (new, will be None on v1.9 export)
+    pub totp_config: Option<TotpConfig>,  // ✅ NEW v1.10: Two-Factor Authentication
+
+    // v1.11 data (new, will be None on v1.10 export)
+    pub profile_picture: Option<ProfilePicture>,  // ✅ NEW v1.11: Profile picture (moved from public identity DNA)
+
+    // Metadata
+    pub export_timestamp: i64,
+    pub dna_version: String,
+    #[serde(default)]
+    pub sections: Vec<ExportSection>,  // Sequence markers for import_resume (empty on pre-marker exports)
+    #[serde(default = "legacy_export_format_version")]
+    pub export_format_version: u16,    // Bundle layout; bundles without it are version 1
+    #[serde(default)]
+    pub exported_by: Option<AgentPubKey>,  // Agent whose data this is (None on older exports)
+    #[serde(default)]
+    pub selected_types: Option<Vec<String>>,  // Entry types export_selective kept (None = full export)
+}
+
+/// Layout version written by export_all_data
+const EXPORT_FORMAT_VERSION: u16 = 1;
+
+/// Bundles written before export_format_version existed use the version 1 layout
+fn legacy_export_format_version() -> u16 {
+    1
+}
+
+/// Position of one entry type within the bundle's import sequence
+/// Items are numbered in import order, so `first_sequence..first_sequence + count` covers this type
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportSection {
+    pub entry_type: String,
+    pub first_sequence: u32,
+    pub count: u32,
+}
+
+/// Export all private data for migration to new DNA version
+/// UPDATED FOR v1.6: Now includes email_permissions (was missing in v1.5!)
+#[hdk_extern]
+pub fn export_all_data(_: ()) -> ExternResult<ExportedData> {
+    debug!("📦 [EXPORT] Starting export of all private data");
+    
+    // Get user profile
+    let user_profile = if let Some(record) = get_user_profile(())? {
+        debug!("📦 [EXPORT] Found user profile");
+        record.entry().to_app_option::<UserProfile>().ok().flatten()
+    } else {
+        debug!("📦 [EXPORT] No user profile found");
+        None
+    };
+    
+    // Get recovery phrase
+    let recovery_phrase = if let Some(record) = get_recovery_phrase(())? {
+        debug!("📦 [EXPORT] Found recovery phrase");
+        record.entry().to_app_option::<RecoveryPhrase>().ok().flatten()
+    } else {
+        debug!("📦 [EXPORT] No recovery phrase found");
+        None
+    };
+    
+    // Get all sessions (deprecated but keep for backward compatibility)
+    let session_records = get_my_sessions(())?;
+    let mut sessions = Vec::new();
+    for record in session_records {
+        if let Some(session) = record.entry().to_app_option::<Session>().ok().flatten() {
+            sessions.push(session);
+        }
+    }
+    debug!("📦 [EXPORT] Found {} sessions", sessions.len());
+    
+    // ✅ CRITICAL: Export email permissions (was missing in v1.5!)
+    let email_permissions = get_email_permissions(())?;
+    debug!("📦 [EXPORT] Found {} email permissions", email_permissions.len());
+    
+    // ✅ v1.7 FIX: Actually export activity data!
+    let login_activities = load_login_activity(10000, 0)?;  // Export all (reasonable max)
+    debug!("📦 [EXPORT] Found {} login activities", login_activities.len());
+    
+    let dashboard_activities = load_dashboard_activity(10000, 0)?;  // Export all (reasonable max)
+    debug!("📦 [EXPORT] Found {} dashboard activities", dashboard_activities.len());
+    
+    let oauth_activities = load_oauth_activity(10000, 0)?;  // Export all (reasonable max)
+    debug!("📦 [EXPORT] Found {} OAuth activities", oauth_activities.len());
+    
+    // Export privacy settings (newest chain wins if duplicates exist)
+    let privacy_settings = if let Some((_, settings)) = newest_privacy_settings()? {
+        debug!("📦 [EXPORT] Found privacy settings");
+        Some(settings)
+    } else {
+        debug!("📦 [EXPORT] No privacy settings found");
+        None
+    };
+    
+    // ✅ v1.9: Export analytics_ids (zero-knowledge analytics)
+    let analytics_ids = get_all_analytics_ids(())?;
+    debug!("📦 [EXPORT] Found {} analytics IDs", analytics_ids.len());
+    
+    // ✅ v1.10: Export TOTP config (2FA)
+    let totp_config = if let Some(record) = get_totp_config(())? {
+        debug!("📦 [EXPORT] Found TOTP config");
+        record.entry().to_app_option::<TotpConfig>().ok().flatten()
+    } else {
+        debug!("📦 [EXPORT] No TOTP config found");
+        None
+    };
+    
+    // ✅ v1.11: Export profile picture (moved from public identity DNA)
+    let profile_picture = if let Some(record) = get_profile_picture(())? {
+        debug!("📦 [EXPORT] Found profile picture");
+        record.entry().to_app_option::<ProfilePicture>().ok().flatten()
+    } else {
+        debug!("📦 [EXPORT] No profile picture found");
+        None
+    };
+
+    let export_timestamp = sys_time()?.as_micros();
+
+    let mut exported_data = ExportedData {
+        user_profile,
+        recovery_phrase,
+        sessions,
+        email_permissions,
+        // ✅ v1.7: Now actually exports activity data!
+        login_activities,
+        dashboard_activities,
+        oauth_activities,
+        privacy_settings,
+        // ✅ v1.9: Now exports analytics_ids!
+        analytics_ids,
+        // ✅ v1.10: Now exports TOTP config!
+        totp_config,
+        // ✅ v1.11: Now exports profile picture!
+        profile_picture,
+        export_timestamp,
+        dna_version: DNA_VERSION.to_string(),
+        sections: Vec::new(),
+        export_format_version: EXPORT_FORMAT_VERSION,
+        exported_by: Some(agent_info()?.agent_initial_pubkey),
+        selected_types: None,
+    };
+    exported_data.sections = export_sections(&plan_import_counts(&exported_data));
+    
+    debug!("📦 [EXPORT] Export complete");
+    Ok(exported_data)
+}
+
+/// Export only the requested entry types; every other collection is left empty
+/// Type names are the section names used in ExportSection (e.g. "user_profile", "email_permissions")
+#[hdk_extern]
+pub fn export_selective(types: Vec<String>) -> ExternResult<ExportedData> {
+    let known: Vec<&str> = ImportPlan::default().counts().iter().map(|(name, _)| *name).collect();
+    let unknown: Vec<&str> = types
+        .iter()
+        .map(String::as_str)
+        .filter(|name| !known.contains(name))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "UnknownEntryType: {}; expected any of {}",
+            unknown.join(", "),
+            known.join(", ")
+        ))));
+    }
+    
+    let mut data = export_all_data(())?;
+    
+    // Clear every collection that wasn't asked for
+    for name in known.iter().filter(|name| !types.iter().any(|requested| requested == *name)) {
+        match *name {
+            "user_profile" => data.user_profile = None,
+            "recovery_phrase" => data.recovery_phrase = None,
+            "sessions" => data.sessions.clear(),
+            "email_permissions" => data.email_permissions.clear(),
+            "login_activities" => data.login_activities.clear(),
+            "dashboard_activities" => data.dashboard_activities.clear(),
+            "oauth_activities" => data.oauth_activities.clear(),
+            "privacy_settings" => data.privacy_settings = None,
+            "analytics_ids" => data.analytics_ids.clear(),
+            "totp_config" => data.totp_config = None,
+            "profile_picture" => data.profile_picture = None,
+            _ => {}
+        }
+    }
+    data.selected_types = Some(types.clone());
+    data.sections = export_sections(&plan_import_counts(&data));
+    
+    debug!("📦 [EXPORT] Selective export of {}", types.join(", "));
+    Ok(data)
+}
+
+/// Import data from an export bundle
+/// UPDATED FOR v1.6: Now handles email_permissions and creates default privacy settings
+#[hdk_extern]
+pub fn import_data(data: ExportedData) -> ExternResult<()> {
+    debug!("📥 [IMPORT] Starting import of exported data from DNA v{}", data.dna_version);
+    
+    run_import(data, 0, None)?;
+    
+    debug!("📥 [IMPORT] Import complete");
+    Ok(())
+}
+
+/// Input for resuming a partially completed import
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportResumeInput {
+    pub data: ExportedData,
+    #[serde(default)]
+    pub from_index: Option<u32>,  // First item to import (None = continue from the stored checkpoint)
+    pub max_items: Option<u32>,   // Batch size (None = import everything remaining)
+}
+
+/// Where an import stands after a batch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportProgress {
+    pub next_index: u32,          // Pass as from_index to continue
+    pub total: u32,
+    pub complete: bool,
+}
+
+/// Import a bundle starting at `from_index`, skipping items already imported
+/// Each batch commits atomically together with an on-chain checkpoint, so a retry without
+/// from_index continues after the last committed item
+#[hdk_extern]
+pub fn import_resume(input: ImportResumeInput) -> ExternResult<ImportProgress> {
+    let plan = plan_import_counts(&input.data);
+    if !input.data.sections.is_empty() && input.data.sections != export_sections(&plan) {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest("ImportMismatch: bundle sections don't match its contents".into())
+        ));
+    }
+    
+    let export_timestamp = input.data.export_timestamp;
+    let total: u32 = plan.counts().iter().map(|(_, count)| count).sum();
+    let checkpoint = latest_import_checkpoint()?;
+    let from_index = match input.from_index {
+        Some(from_index) => from_index,
+        None => resume_index(checkpoint.as_ref().map(|(_, checkpoint)| checkpoint), export_timestamp, total),
+    };
+    
+    debug!("📥 [IMPORT] Resuming import from item {}", from_index);
+    let progress = run_import(input.data, from_index, input.max_items)?;
+    save_import_checkpoint(checkpoint, export_timestamp, &progress)?;
+    Ok(progress)
+}
+
+/// Where to continue importing a bundle: the checkpoint's next_index if it was written for
+/// this same bundle (matching export_timestamp and item count), otherwise the beginning
+fn resume_index(checkpoint: Option<&ImportCheckpoint>, export_timestamp: i64, total: u32) -> u32 {
+    checkpoint
+        .filter(|checkpoint| checkpoint.export_timestamp == export_timestamp && checkpoint.total == total)
+        .map_or(0, |checkpoint| checkpoint.next_index.min(total))
+}
+
+/// Head of the agent's import checkpoint chain, if an import has ever been resumed
+fn latest_import_checkpoint() -> ExternResult<Option<(Record, ImportCheckpoint)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToImportCheckpoint)?,
+        GetStrategy::default()
+    )?;
+    let Some(link) = links.into_iter().max_by_key(|link| link.timestamp) else {
+        return Ok(None);
+    };
+    let checkpoint_hash = ActionHash::try_from(link.target)
+        .map_err(|_| wasm_error!("Invalid import checkpoint hash"))?;
+    
+    let Some(head) = latest_in_chain(checkpoint_hash)? else {
+        return Ok(None);
+    };
+    Ok(head
+        .entry()
+        .to_app_option::<ImportCheckpoint>()
+        .ok()
+        .flatten()
+        .map(|checkpoint| (head, checkpoint)))
+}
+
+/// Record how far the import got, updating the existing checkpoint chain if there is one
+fn save_import_checkpoint(
+    existing: Option<(Record, ImportCheckpoint)>,
+    export_timestamp: i64,
+    progress: &ImportProgress,
+) -> ExternResult<()> {
+    let now = sys_time()?.as_micros();
+    
+    match existing {
+        Some((head, previous)) => {
+            let checkpoint = ImportCheckpoint {
+                export_timestamp,
+                total: progress.total,
+                next_index: progress.next_index,
+                created_at: previous.created_at,
+                updated_at: now,
+            };
+            update_entry(
+                head.action_address().clone(),
+                &EntryZomes::IntegrityPrivateData(EntryTypes::ImportCheckpoint(checkpoint)),
+            )?;
+        }
+        None => {
+            let checkpoint = ImportCheckpoint {
+                export_timestamp,
+                total: progress.total,
+                next_index: progress.next_index,
+                created_at: now,
+                updated_at: now,
+            };
+            let checkpoint_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+                EntryTypes::ImportCheckpoint(checkpoint)
+            ))?;
+            let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+            create_link(my_agent_pub_key, checkpoint_hash, LinkTypes::AgentToImportCheckpoint, ())?;
+        }
+    }
+    Ok(())
+}
+
+/// One importable item from an export bundle
+enum ImportItem {
+    UserProfile(UserProfile),
+    RecoveryPhrase(RecoveryPhrase),
+    Session(Session),
+    EmailPermission(EmailPermission),
+    LoginActivity(LoginActivity),
+    DashboardActivity(DashboardActivity),
+    OAuthActivity(OAuthActivity),
+    PrivacySettings(Option<PrivacySettings>),  // None = create defaults if the agent has none (v1.5 → v1.6 migration)
+    AnalyticsId(AppAnalyticsId),
+    TotpConfig(TotpConfig),
+    ProfilePicture(ProfilePicture),
+}
+
+/// Bring a bundle in any supported export format up to the current layout
+/// Add an arm here (translating into the current layout) whenever EXPORT_FORMAT_VERSION is bumped
+fn decode_export_format(data: ExportedData) -> ExternResult<ExportedData> {
+    match data.export_format_version {
+        1 => Ok(data),
+        version => Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "UnsupportedExportFormat: bundle uses export format {}, this DNA reads up to {}",
+            version, EXPORT_FORMAT_VERSION
+        )))),
+    }
+}
+
+/// Flatten a bundle into items in import order
+/// Must stay in the same order as ImportPlan::counts so sequence numbers line up with the sections
+fn import_items(data: ExportedData) -> Vec<ImportItem> {
+    let mut items = Vec::new();
+    items.extend(data.user_profile.map(ImportItem::UserProfile));
+    items.extend(data.recovery_phrase.map(ImportItem::RecoveryPhrase));
+    items.extend(data.sessions.into_iter().map(ImportItem::Session));
+    items.extend(data.email_permissions.into_iter().map(ImportItem::EmailPermission));
+    items.extend(
+        in_store_order(data.login_activities, |activity| (activity.seq, activity.timestamp))
+            .into_iter()
+            .map(ImportItem::LoginActivity)
+    );
+    items.extend(
+        in_store_order(data.dashboard_activities, |activity| (activity.seq, activity.visit_timestamp))
+            .into_iter()
+            .map(ImportItem::DashboardActivity)
+    );
+    items.extend(
+        in_store_order(data.oauth_activities, |activity| (activity.seq, activity.timestamp))
+            .into_iter()
+            .map(ImportItem::OAuthActivity)
+    );
+    if imports_privacy_settings(&data) {
+        items.push(ImportItem::PrivacySettings(data.privacy_settings));
+    }
+    items.extend(data.analytics_ids.into_iter().map(ImportItem::AnalyticsId));
+    items.extend(data.totp_config.map(ImportItem::TotpConfig));
+    items.extend(data.profile_picture.map(ImportItem::ProfilePicture));
+    items
+}
+
+/// Exported activities in the order they were first stored: by seq, then timestamp
+/// Entries from before seq existed (None) come first. Imports restamp seq in this order, so
+/// imported history keeps its relative order and sits after whatever the agent already has
+fn in_store_order<T>(mut activities: Vec<T>, store_key: impl Fn(&T) -> (Option<u64>, i64)) -> Vec<T> {
+    activities.sort_by_key(store_key);
+    activities
+}
+
+/// Whether importing the bundle writes privacy settings: the exported ones, or defaults when a
+/// full export had none. Selective exports that left privacy settings out don't touch them
+fn imports_privacy_settings(data: &ExportedData) -> bool {
+    data.privacy_settings.is_some()
+        || data
+            .selected_types
+            .as_ref()
+            .is_none_or(|types| types.iter().any(|entry_type| entry_type == "privacy_settings"))
+}
+
+/// Max items of any one type accepted in an import bundle (guards against chain bloat)
+const MAX_IMPORT_ITEMS_PER_TYPE: u32 = 50_000;
+
+/// Entry types in the planned import that exceed MAX_IMPORT_ITEMS_PER_TYPE
+fn oversized_import_types(plan: &ImportPlan) -> Vec<String> {
+    plan
+        .counts()
+        .into_iter()
+        .filter(|(_, count)| *count > MAX_IMPORT_ITEMS_PER_TYPE)
+        .map(|(entry_type, count)| format!("{} has {} items", entry_type, count))
+        .collect()
+}
+
+/// Reject imports with too many items of any type, before anything is written
+fn ensure_import_within_limits(plan: &ImportPlan) -> ExternResult<()> {
+    let oversized = oversized_import_types(plan);
+    if oversized.is_empty() {
+        return Ok(());
+    }
+    
+    Err(wasm_error!(WasmErrorInner::Guest(format!(
+        "ImportTooLarge: limit is {} items per type; {}",
+        MAX_IMPORT_ITEMS_PER_TYPE,
+        oversized.join("; ")
+    ))))
+}
+
+/// First DNA version (major, minor) whose exports always record exported_by
+const EXPORTED_BY_SINCE: (u32, u32) = (1, 12);
+
+/// (major, minor) of a DNA version string like "1.12" or "1.12.3"; None if it doesn't parse
+fn dna_major_minor(dna_version: &str) -> Option<(u32, u32)> {
+    let mut parts = dna_version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// What's wrong with a bundle's claimed author, if anything
+/// Imported entries are always authored by the importing agent, so a foreign exported_by means
+/// the bundle is being passed off as someone else's. Bundles from a DNA that records exported_by
+/// must carry it; older exports (e.g., v1.11 for migration) predate the field and may omit it
+fn import_author_problem(exported_by: Option<&AgentPubKey>, dna_version: &str, importing_agent: &AgentPubKey) -> Option<String> {
+    match exported_by {
+        Some(exported_by) if exported_by != importing_agent => Some(format!(
+            "bundle was exported by {}, not the importing agent {}", exported_by, importing_agent
+        )),
+        Some(_) => None,
+        None => {
+            let records_author = dna_major_minor(dna_version)
+                .is_none_or(|version| version >= EXPORTED_BY_SINCE);
+            records_author.then(|| format!("bundle from DNA v{} is missing exported_by", dna_version))
+        }
+    }
+}
+
+/// Reject bundles that claim to hold another agent's data, or that had their author stripped
+fn ensure_import_author(data: &ExportedData) -> ExternResult<()> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    match import_author_problem(data.exported_by.as_ref(), &data.dna_version, &my_agent_pub_key) {
+        Some(problem) => Err(wasm_error!(WasmErrorInner::Guest(format!("ForeignAuthor: {}", problem)))),
+        None => Ok(()),
+    }
+}
+
+/// Import items `from_index..` (at most `max_items` of them) and report progress
+fn run_import(data: ExportedData, from_index: u32, max_items: Option<u32>) -> ExternResult<ImportProgress> {
+    let data = decode_export_format(data)?;
+    ensure_import_within_limits(&ImportPlan::from(&data))?;
+    ensure_import_author(&data)?;
+    
+    let items = import_items(data);
+    let total = items.len() as u32;
+    let batch_size = max_items.map_or(usize::MAX, |max_items| max_items as usize);
+    
+    let mut next_index = from_index.min(total);
+    for item in items.into_iter().skip(next_index as usize).take(batch_size) {
+        import_item(item)?;
+        next_index += 1;
+    }
+    
+    debug!("📥 [IMPORT] Imported up to item {} of {}", next_index, total);
+    Ok(ImportProgress {
+        next_index,
+        total,
+        complete: next_index == total,
+    })
+}
+
+/// Write a single imported item with its agent link
+fn import_item(item: ImportItem) -> ExternResult<()> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    match item {
+        ImportItem::UserProfile(profile) => {
+            store_user_profile(profile.into())?;
+        }
+        ImportItem::RecoveryPhrase(recovery_phrase) => {
+            store_recovery_phrase(recovery_phrase)?;
+        }
+        // Sessions are deprecated but kept for backward compatibility
+        // Written without eviction: every exported session is restored, whatever max_sessions says
+        ImportItem::Session(session) => {
+            create_session(session, None)?;
+        }
+        // ✅ CRITICAL: Recreate permission with proper linking
+        ImportItem::EmailPermission(mut permission) => {
+            // Older exports may carry un-normalized names; store them the way grants do
+            permission.service_name = normalize_service_name(&permission.service_name);
+            let permission_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+                EntryTypes::EmailPermission(permission)
+            ))?;
+            create_link(my_agent_pub_key, permission_hash, LinkTypes::AgentToEmailPermissions, ())?;
+        }
+        // Restored without login dedupe or tracking toggles; seq is restamped after existing activity
+        ImportItem::LoginActivity(activity) => {
+            create_login_activity(activity, None)?;
+        }
+        ImportItem::DashboardActivity(activity) => {
+            create_dashboard_activity(activity, None)?;
+        }
+        ImportItem::OAuthActivity(activity) => {
+            create_oauth_activity(activity, None)?;
+        }
+        ImportItem::PrivacySettings(None) => {
+            if get_privacy_settings(())?.is_some() {
+                debug!("📥 [IMPORT] No privacy settings in export, keeping the existing ones");
+            } else {
+                debug!("📥 [IMPORT] No privacy settings in export, creating defaults for v1.6");
+                create_default_privacy_settings(())?;
+            }
+        }
+        ImportItem::PrivacySettings(Some(settings)) => {
+            store_imported_privacy_settings(settings)?;
+        }
+        // ✅ v1.9: Will be empty on v1.8 import - analytics_ids are generated on-demand per app
+        ImportItem::AnalyticsId(analytics_id) => {
+            let tag = LinkTag::from(analytics_id.app_id.as_bytes().to_vec());
+            let analytics_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+                EntryTypes::AppAnalyticsId(analytics_id)
+            ))?;
+            create_link(my_agent_pub_key, analytics_hash, LinkTypes::AgentToAppAnalyticsId, tag)?;
+        }
+        // ✅ v1.10: Will be None when importing from v1.9 data — 2FA wasn't available yet
+        ImportItem::TotpConfig(totp) => {
+            let totp_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+                EntryTypes::TotpConfig(totp)
+            ))?;
+            create_link(my_agent_pub_key, totp_hash, LinkTypes::AgentToTotpConfig, ())?;
+        }
+        // ✅ v1.11: Will be None when importing from v1.10 data — profile picture was on identity DNA
+        // The API migration path will populate this from the identity DNA during migration
+        ImportItem::ProfilePicture(picture) => {
+            let picture_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+                EntryTypes::ProfilePicture(picture)
+            ))?;
+            create_link(my_agent_pub_key, picture_hash, LinkTypes::AgentToProfilePicture, ())?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Sequence markers for a bundle, one per entry type that has items
+fn export_sections(plan: &ImportPlan) -> Vec<ExportSection> {
+    let mut sections = Vec::new();
+    let mut first_sequence = 0;
+    for (entry_type, count) in plan.counts() {
+        if count > 0 {
+            sections.push(ExportSection {
+                entry_type: entry_type.to_string(),
+                first_sequence,
+                count,
+            });
+        }
+        first_sequence += count;
+    }
+    sections
+}
+
+/// Export all private data as a deflate-compressed MessagePack bundle
+/// Activity-heavy exports shrink considerably, which matters for data-portability downloads
+#[hdk_extern]
+pub fn export_all_data_compressed(_: ()) -> ExternResult<Vec<u8>> {
+    compress_bundle(&export_all_data(())?)
+}
+
+/// Serialize a bundle to MessagePack and deflate it
+fn compress_bundle(data: &ExportedData) -> ExternResult<Vec<u8>> {
+    let serialized = holochain_serialized_bytes::encode(data)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Failed to serialize export: {:?}", e))))?;
+    
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder.write_all(&serialized)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Failed to compress export: {}", e))))?;
+    
+    debug!("📦 [EXPORT] Compressed export from {} to {} bytes", serialized.len(), compressed.len());
+    Ok(compressed)
+}
+
+/// Max size of a decompressed bundle - well above a full export at MAX_IMPORT_ITEMS_PER_TYPE
+/// Stops a small, highly compressed payload from expanding until the zome runs out of memory
+const MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Inflate a compressed bundle, rejecting it once the output would exceed `limit` bytes
+fn decompress_bundle(bytes: &[u8], limit: u64) -> ExternResult<Vec<u8>> {
+    let mut serialized = Vec::new();
+    // Read one byte past the limit so an over-limit bundle is detected rather than silently truncated
+    DeflateDecoder::new(bytes)
+        .take(limit + 1)
+        .read_to_end(&mut serialized)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Failed to decompress bundle: {}", e))))?;
+    
+    if serialized.len() as u64 > limit {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "ImportTooLarge: decompressed bundle exceeds {} bytes", limit
+        ))));
+    }
+    Ok(serialized)
+}
+
+/// Decompress a bundle from export_all_data_compressed and import it
+#[hdk_extern]
+pub fn import_data_compressed(bytes: Vec<u8>) -> ExternResult<()> {
+    import_data(decode_compressed_bundle(&bytes, MAX_DECOMPRESSED_BYTES)?)
+}
+
+/// Inflate (up to `limit` bytes) and deserialize a bundle from compress_bundle
+fn decode_compressed_bundle(bytes: &[u8], limit: u64) -> ExternResult<ExportedData> {
+    let serialized = decompress_bundle(bytes, limit)?;
+    
+    holochain_serialized_bytes::decode(&serialized)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Failed to deserialize bundle: {:?}", e))))
+}
+
+/// Activity collections and privacy settings, importable without touching profile or recovery data
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActivityExport {
+    #[serde(default)]
+    pub login_activities: Vec<LoginActivity>,
+    #[serde(default)]
+    pub dashboard_activities: Vec<DashboardActivity>,
+    #[serde(default)]
+    pub oauth_activities: Vec<OAuthActivity>,
+    pub privacy_settings: Option<PrivacySettings>,
+}
+
+/// Items processed by import_activities
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportCounts {
+    pub login_activities: u32,
+    pub dashboard_activities: u32,
+    pub oauth_activities: u32,
+    pub privacy_settings: u32,
+}
+
+/// Write imported privacy settings: update the existing chain, or create it if the agent has none
+/// Integrity rejects a second live PrivacySettings create, so imports must never blindly create
+fn store_imported_privacy_settings(settings: PrivacySettings) -> ExternResult<()> {
+    if get_privacy_settings(())?.is_some() {
+        update_privacy_settings(settings)?;
+    } else {
+        let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+        let settings_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+            EntryTypes::PrivacySettings(settings)
+        ))?;
+        create_link(my_agent_pub_key, settings_hash, LinkTypes::AgentToPrivacySettings, ())?;
+    }
+    Ok(())
+}
+
+/// Import activity history (and optionally privacy settings) for a large backfill
+/// Independent of import_data, so the profile, recovery phrase and credentials are never touched
+/// Imported privacy settings update the existing chain rather than creating a second one
+#[hdk_extern]
+pub fn import_activities(activities: ActivityExport) -> ExternResult<ImportCounts> {
+    ensure_import_within_limits(&ImportPlan::from(&activities))?;
+    
+    debug!(
+        "📥 [IMPORT] Importing {} login, {} dashboard and {} OAuth activities",
+        activities.login_activities.len(),
+        activities.dashboard_activities.len(),
+        activities.oauth_activities.len()
+    );
+    
+    let mut counts = ImportCounts {
+        login_activities: 0,
+        dashboard_activities: 0,
+        oauth_activities: 0,
+        privacy_settings: 0,
+    };
+    
+    for item in activity_import_items(activities) {
+        match item {
+            ImportItem::LoginActivity(_) => counts.login_activities += 1,
+            ImportItem::DashboardActivity(_) => counts.dashboard_activities += 1,
+            ImportItem::OAuthActivity(_) => counts.oauth_activities += 1,
+            ImportItem::PrivacySettings(_) => counts.privacy_settings += 1,
+            _ => {}
+        }
+        import_item(item)?;
+    }
+    
+    debug!("📥 [IMPORT] Activity import complete: {:?}", counts);
+    Ok(counts)
+}
+
+/// Import items for an activity backfill, written the way import_data writes them (oldest first)
+/// Only activity and privacy settings items, so profile and recovery data are never touched
+fn activity_import_items(activities: ActivityExport) -> Vec<ImportItem> {
+    let mut items = Vec::new();
+    items.extend(
+        in_store_order(activities.login_activities, |activity| (activity.seq, activity.timestamp))
+            .into_iter()
+            .map(ImportItem::LoginActivity)
+    );
+    items.extend(
+        in_store_order(activities.dashboard_activities, |activity| (activity.seq, activity.visit_timestamp))
+            .into_iter()
+            .map(ImportItem::DashboardActivity)
+    );
+    items.extend(
+        in_store_order(activities.oauth_activities, |activity| (activity.seq, activity.timestamp))
+            .into_iter()
+            .map(ImportItem::OAuthActivity)
+    );
+    items.extend(activities.privacy_settings.map(|settings| ImportItem::PrivacySettings(Some(settings))));
+    items
+}
+
+/// Whether an export from the given DNA version can be imported into this one
+/// Any 1.x export up to the current version is supported
+fn is_supported_dna_version(dna_version: &str) -> bool {
+    let current_minor: u32 = DNA_VERSION
+        .split('.')
+        .nth(1)
+        .and_then(|minor| minor.parse().ok())
+        .unwrap_or(0);
+
+    match dna_version.split_once('.') {
+        Some(("1", minor)) => minor.parse::<u32>().is_ok_and(|minor| minor <= current_minor),
+        _ => false,
+    }
+}
+
+/// Check an export bundle's structure without writing anything
+/// Returns a list of problems (empty if the bundle is valid)
+#[hdk_extern]
+pub fn validate_export_bundle(data: ExportedData) -> ExternResult<Vec<String>> {
+    let mut problems = Vec::new();
+
+    if !is_supported_dna_version(&data.dna_version) {
+        problems.push(format!("Unsupported dna_version: {}", data.dna_version));
+    }
+    if data.export_format_version == 0 || data.export_format_version > EXPORT_FORMAT_VERSION {
+        problems.push(format!("Unsupported export_format_version: {}", data.export_format_version));
+    }
+    check_timestamp("export_timestamp", data.export_timestamp, &mut problems);
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    if let Some(problem) = import_author_problem(data.exported_by.as_ref(), &data.dna_version, &my_agent_pub_key) {
+        problems.push(problem);
+    }
+    for oversized in oversized_import_types(&ImportPlan::from(&data)) {
+        problems.push(format!("Too many items (limit {}): {}", MAX_IMPORT_ITEMS_PER_TYPE, oversized));
+    }
+
+    if let Some(profile) = &data.user_profile {
+        for violation in validate_user_profile_input(profile, false) {
+            problems.push(format!("user_profile: {}", violation));
+        }
+    }
+    if let Some(recovery_phrase) = &data.recovery_phrase {
+        for violation in validate_recovery_phrase_input(recovery_phrase) {
+            problems.push(format!("recovery_phrase: {}", violation));
+        }
+    }
+
+    for (index, permission) in data.email_permissions.iter().enumerate() {
+        if permission.service_name.trim().is_empty() {
+            problems.push(format!("email_permissions[{}]: service_name is required", index));
+        }
+        if permission.granted && permission.revoked_at.is_some() {
+            problems.push(format!("email_permissions[{}]: granted but revoked_at is set", index));
+        }
+        check_timestamp(&format!("email_permissions[{}].created_at", index), permission.created_at, &mut problems);
+    }
+
+    for (index, activity) in data.login_activities.iter().enumerate() {
+        check_timestamp(&format!("login_activities[{}].timestamp", index), activity.timestamp, &mut problems);
+    }
+    for (index, activity) in data.dashboard_activities.iter().enumerate() {
+        check_timestamp(&format!("dashboard_activities[{}].visit_timestamp", index), activity.visit_timestamp, &mut problems);
+    }
+    for (index, activity) in data.oauth_activities.iter().enumerate() {
+        check_timestamp(&format!("oauth_activities[{}].timestamp", index), activity.timestamp, &mut problems);
+    }
+
+    if let Some(totp) = &data.totp_config {
+        check_base64_field("totp_config.encrypted_secret", &totp.encrypted_secret, &mut problems);
+        check_base64_field("totp_config.nonce", &totp.nonce, &mut problems);
+        check_base64_field("totp_config.salt", &totp.salt, &mut problems);
+        check_base64_field("totp_config.tag", &totp.tag, &mut problems);
+    }
+
+    debug!("📥 [VALIDATE] Export bundle has {} problems", problems.len());
+    Ok(problems)
+}
+
+/// Expected per-type entry counts for an import (dry-run of import_data)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ImportPlan {
+    pub user_profile: u32,
+    pub recovery_phrase: u32,
+    pub sessions: u32,
+    pub email_permissions: u32,
+    pub login_activities: u32,
+    pub dashboard_activities: u32,
+    pub oauth_activities: u32,
+    pub privacy_settings: u32,
+    pub analytics_ids: u32,
+    pub totp_config: u32,
+    pub profile_picture: u32,
+}
+
+impl ImportPlan {
+    /// Per-type counts in import order
+    fn counts(&self) -> [(&'static str, u32); 11] {
+        [
+            ("user_profile", self.user_profile),
+            ("recovery_phrase", self.recovery_phrase),
+            ("sessions", self.sessions),
+            ("email_permissions", self.email_permissions),
+            ("login_activities", self.login_activities),
+            ("dashboard_activities", self.dashboard_activities),
+            ("oauth_activities", self.oauth_activities),
+            ("privacy_settings", self.privacy_settings),
+            ("analytics_ids", self.analytics_ids),
+            ("totp_config", self.totp_config),
+            ("profile_picture", self.profile_picture),
+        ]
+    }
+}
+
+impl From<&ExportedData> for ImportPlan {
+    fn from(data: &ExportedData) -> Self {
+        ImportPlan {
+            user_profile: data.user_profile.is_some() as u32,
+            recovery_phrase: data.recovery_phrase.is_some() as u32,
+            sessions: data.sessions.len() as u32,
+            email_permissions: data.email_permissions.len() as u32,
+            login_activities: data.login_activities.len() as u32,
+            dashboard_activities: data.dashboard_activities.len() as u32,
+            oauth_activities: data.oauth_activities.len() as u32,
+            privacy_settings: data.privacy_settings.is_some() as u32,
+            analytics_ids: data.analytics_ids.len() as u32,
+            totp_config: data.totp_config.is_some() as u32,
+            profile_picture: data.profile_picture.is_some() as u32,
+        }
+    }
+}
+
+impl From<&ActivityExport> for ImportPlan {
+    fn from(activities: &ActivityExport) -> Self {
+        ImportPlan {
+            login_activities: activities.login_activities.len() as u32,
+            dashboard_activities: activities.dashboard_activities.len() as u32,
+            oauth_activities: activities.oauth_activities.len() as u32,
+            privacy_settings: activities.privacy_settings.is_some() as u32,
+            ..ImportPlan::default()
+        }
+    }
+}
+
+/// Result of comparing one entry type's expected and actual counts
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportTypeCheck {
+    pub entry_type: String,
+    pub expected: u32,
+    pub actual: u32,
+    pub matches: bool,
+}
+
+/// Post-import verification report
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportVerification {
+    pub checks: Vec<ImportTypeCheck>,
+    pub all_match: bool,
+}
+
+/// Dry-run of import_data: returns the counts an import of this bundle should produce
+/// Privacy settings are expected unless a selective export left them out, because import_data
+/// creates defaults when a full export has none
+#[hdk_extern]
+pub fn plan_import(data: ExportedData) -> ExternResult<ImportPlan> {
+    Ok(plan_import_counts(&data))
+}
+
+/// Expected counts for a bundle, one per item import_items yields
+fn plan_import_counts(data: &ExportedData) -> ImportPlan {
+    let mut plan = ImportPlan::from(data);
+    plan.privacy_settings = imports_privacy_settings(data) as u32;
+    plan
+}
+
+/// Verify that every item from an import landed, comparing current counts to the plan
+#[hdk_extern]
+pub fn verify_import(expected: ImportPlan) -> ExternResult<ImportVerification> {
+    let actual = ImportPlan::from(&export_all_data(())?);
+    let verification = compare_import_counts(&expected, &actual);
+
+    for check in verification.checks.iter().filter(|check| !check.matches) {
+        debug!("📥 [VERIFY] Mismatch for {}: expected {}, found {}", check.entry_type, check.expected, check.actual);
+    }
+
+    Ok(verification)
+}
+
+/// Per-type comparison of the planned counts against what the agent now holds
+fn compare_import_counts(expected: &ImportPlan, actual: &ImportPlan) -> ImportVerification {
+    let checks: Vec<ImportTypeCheck> = expected
+        .counts()
+        .into_iter()
+        .zip(actual.counts())
+        .map(|((entry_type, expected), (_, actual))| ImportTypeCheck {
+            entry_type: entry_type.to_string(),
+            expected,
+            actual,
+            matches: expected == actual,
+        })
+        .collect();
+
+    let all_match = checks.iter().all(|check| check.matches);
+    ImportVerification { checks, all_match }
+}
+
+/// Action hashes of every entry on this agent's source chain, grouped by entry type
+/// Includes each create and update (not just chain heads), so it lists everything a backup should hold
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BackupManifest {
+    pub user_profile: Vec<ActionHash>,
+    pub recovery_phrase: Vec<ActionHash>,
+    pub sessions: Vec<ActionHash>,
+    pub email_permissions: Vec<ActionHash>,
+    pub login_activity: Vec<ActionHash>,
+    pub dashboard_activity: Vec<ActionHash>,
+    pub oauth_activity: Vec<ActionHash>,
+    pub privacy_settings: Vec<ActionHash>,
+    pub analytics_ids: Vec<ActionHash>,
+    pub totp_config: Vec<ActionHash>,
+    pub profile_picture: Vec<ActionHash>,
+    pub total: u32,
+}
+
+/// Action hashes of every create/update of one entry type on the local source chain, oldest first
+fn chain_action_hashes(entry_type: UnitEntryTypes) -> ExternResult<Vec<ActionHash>> {
+    let filter = ChainQueryFilter::new()
+        .entry_type(EntryType::try_from(entry_type)?)
+        .include_entries(false);
+    
+    Ok(query(filter)?
+        .into_iter()
+        .map(|record| record.action_address().clone())
+        .collect())
+}
+
+/// Manifest of every entry action hash this agent holds, for backup tools to verify completeness
+/// Read from the local source chain, so it never touches the network
+#[hdk_extern]
+pub fn get_backup_manifest(_: ()) -> ExternResult<BackupManifest> {
+    let manifest = build_backup_manifest(chain_action_hashes)?;
+    debug!("📦 [EXPORT] Backup manifest lists {} actions", manifest.total);
+    Ok(manifest)
+}
+
+/// Manifest from `hashes`, which lists the action hashes of one entry type
+/// Import checkpoints are bookkeeping for import_resume, not user data, so they aren't listed
+fn build_backup_manifest(
+    mut hashes: impl FnMut(UnitEntryTypes) -> ExternResult<Vec<ActionHash>>,
+) -> ExternResult<BackupManifest> {
+    let mut manifest = BackupManifest {
+        user_profile: hashes(UnitEntryTypes::UserProfile)?,
+        recovery_phrase: hashes(UnitEntryTypes::RecoveryPhrase)?,
+        sessions: hashes(UnitEntryTypes::Session)?,
+        email_permissions: hashes(UnitEntryTypes::EmailPermission)?,
+        login_activity: hashes(UnitEntryTypes::LoginActivity)?,
+        dashboard_activity: hashes(UnitEntryTypes::DashboardActivity)?,
+        oauth_activity: hashes(UnitEntryTypes::OAuthActivity)?,
+        privacy_settings: hashes(UnitEntryTypes::PrivacySettings)?,
+        analytics_ids: hashes(UnitEntryTypes::AppAnalyticsId)?,
+        totp_config: hashes(UnitEntryTypes::TotpConfig)?,
+        profile_picture: hashes(UnitEntryTypes::ProfilePicture)?,
+        total: 0,
+    };
+    
+    manifest.total = [
+        &manifest.user_profile,
+        &manifest.recovery_phrase,
+        &manifest.sessions,
+        &manifest.email_permissions,
+        &manifest.login_activity,
+        &manifest.dashboard_activity,
+        &manifest.oauth_activity,
+        &manifest.privacy_settings,
+        &manifest.analytics_ids,
+        &manifest.totp_config,
+        &manifest.profile_picture,
+    ]
+        .iter()
+        .map(|hashes| hashes.len() as u32)
+        .sum();
+    
+    Ok(manifest)
+}
+
+// ============================================================================
+// EMAIL PERMISSIONS - NEW IN v1.1
+// ============================================================================
+
+/// Input for granting email permission
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GrantPermissionInput {
+    pub service_name: String,
+    pub purpose: String,
+    pub expires_in_seconds: Option<i64>,  // None = consent never expires
+    #[serde(default)]
+    pub category: Option<PermissionCategory>,  // None = keep the existing category (Other for new permissions)
+}
+
+/// Canonical form of a service name (trimmed, lowercase) so "Billing " and "billing" match
+fn normalize_service_name(service_name: &str) -> String {
+    service_name.trim().to_lowercase()
+}
+
+/// Whether a stored permission belongs to the given (already normalized) service name
+/// Stored names are normalized too, in case they were written before normalization existed
+fn is_same_service(permission: &EmailPermission, normalized_service_name: &str) -> bool {
+    normalize_service_name(&permission.service_name) == normalized_service_name
+}
+
+/// A permission is only active while granted and not past its expiry
+fn is_permission_active(permission: &EmailPermission, now: i64) -> bool {
+    permission.granted && permission.expires_at.is_none_or(|expires_at| expires_at > now)
+}
+
+/// Every linked permission that hasn't been deleted, as (head action hash, latest version)
+/// Writes must update the head hash; updating the linked original forks the chain
+fn permission_heads() -> ExternResult<Vec<(ActionHash, EmailPermission)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToEmailPermissions)?,
+        GetStrategy::default()
+    )?;
+    
+    permission_heads_from(links, details_with(GetStrategy::default()))
+}
+
+/// Latest live permission behind each link, loading records through `fetch`
+fn permission_heads_from(
+    links: Vec<Link>,
+    mut fetch: impl FnMut(&ActionHash) -> ExternResult<Option<Details>>,
+) -> ExternResult<Vec<(ActionHash, EmailPermission)>> {
+    let mut permissions = Vec::new();
+    for link in links {
+        let permission_hash = ActionHash::try_from(link.target)
+            .map_err(|_| wasm_error!("Invalid permission hash"))?;
+        
+        // Skip tombstoned permissions - the link may outlive the deleted entry
+        if let Some(Details::Record(details)) = fetch(&permission_hash)? {
+            if !details.deletes.is_empty() {
+                debug!("🔐 [PERMISSION] Skipping deleted permission");
+                continue;
+            }
+        }
+        
+        // Follow the update chain to get the latest version
+        if let Some((head, _)) = walk_update_chain(permission_hash, &mut fetch)? {
+            if let Some(permission) = head.entry().to_app_option::<EmailPermission>().ok().flatten() {
+                permissions.push((head.action_address().clone(), permission));
+            }
+        }
+    }
+    
+    Ok(permissions)
+}
+
+// Permission state transitions take `now` explicitly so timestamps are deterministic
+// The #[hdk_extern] wrappers pass in sys_time()
+
+/// Mark a permission as granted at `now`, optionally recording usage in the same write
+fn apply_grant(permission: &mut EmailPermission, expires_at: Option<i64>, record_usage: bool, now: i64) {
+    permission.granted = true;
+    permission.granted_at = Some(now);
+    permission.revoked_at = None;
+    permission.expires_at = expires_at;
+    if record_usage {
+        permission.last_used_at = Some(now);
+    }
+    permission.updated_at = now;
+}
+
+/// Mark a permission as revoked at `now`
+fn apply_revoke(permission: &mut EmailPermission, now: i64) {
+    permission.granted = false;
+    permission.revoked_at = Some(now);
+    permission.updated_at = now;
+}
+
+/// Record that the permission was used at `now`
+fn apply_usage(permission: &mut EmailPermission, now: i64) {
+    permission.last_used_at = Some(now);
+    permission.updated_at = now;
+}
+
+/// Build a brand-new granted permission stamped with `now`
+fn new_granted_permission(
+    service_name: String,
+    purpose: String,
+    expires_at: Option<i64>,
+    category: PermissionCategory,
+    record_usage: bool,
+    now: i64,
+) -> EmailPermission {
+    let mut permission = EmailPermission {
+        service_name,
+        purpose,
+        granted: false,
+        granted_at: None,
+        revoked_at: None,
+        last_used_at: None,
+        expires_at: None,
+        created_at: now,
+        updated_at: now,
+        category,
+    };
+    apply_grant(&mut permission, expires_at, record_usage, now);
+    permission
+}
+
+/// The permission a grant writes: the existing one re-granted, or a new one for `service_name`
+/// A re-grant keeps the existing category unless the input sets one
+fn granted_permission(
+    existing: Option<EmailPermission>,
+    service_name: String,
+    input: GrantPermissionInput,
+    expires_at: Option<i64>,
+    record_usage: bool,
+    now: i64,
+) -> EmailPermission {
+    match existing {
+        Some(mut permission) => {
+            apply_grant(&mut permission, expires_at, record_usage, now);
+            if let Some(category) = input.category {
+                permission.category = category;
+            }
+            permission
+        }
+        None => new_granted_permission(
+            service_name, input.purpose, expires_at, input.category.unwrap_or_default(), record_usage, now,
+        ),
+    }
+}
+
+/// Absolute expiry for a consent lasting `expires_in_seconds` from `now` (None = never expires)
+/// Rejects non-positive durations and ones that would overflow the microsecond timestamp
+fn permission_expiry(now: i64, expires_in_seconds: Option<i64>) -> ExternResult<Option<i64>> {
+    let Some(seconds) = expires_in_seconds else {
+        return Ok(None);
+    };
+    if seconds <= 0 {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "InvalidExpiry: expires_in_seconds must be positive, got {}", seconds
+        ))));
+    }
+    seconds
+        .checked_mul(1_000_000)
+        .and_then(|micros| now.checked_add(micros))
+        .map(Some)
+        .ok_or(wasm_error!(WasmErrorInner::Guest(format!(
+            "InvalidExpiry: expires_in_seconds {} is too far in the future", seconds
+        ))))
+}
+
+/// Grant or update email permission for a service
+#[hdk_extern]
+pub fn grant_email_permission(input: GrantPermissionInput) -> ExternResult<ActionHash> {
+    upsert_granted_permission(input, false)
+}
+
+/// Grant email permission and record its first usage in a single write
+/// Avoids the extra update-chain hop of grant_email_permission + record_permission_usage
+#[hdk_extern]
+pub fn grant_and_use_permission(input: GrantPermissionInput) -> ExternResult<ActionHash> {
+    upsert_granted_permission(input, true)
+}
+
+/// Create or update a granted permission, optionally stamping last_used_at in the same entry
+fn upsert_granted_permission(input: GrantPermissionInput, record_usage: bool) -> ExternResult<ActionHash> {
+    let service_name = normalize_service_name(&input.service_name);
+    debug!("🔐 [PERMISSION] Granting email permission for service: {}", service_name);
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+    let expires_at = permission_expiry(now, input.expires_in_seconds)?;
+    
+    // Update the existing permission for this service from its chain head
+    let (head_hash, existing) = permission_heads()?
+        .into_iter()
+        .find(|(_, permission)| is_same_service(permission, &service_name))
+        .unzip();
+    let permission = granted_permission(existing, service_name, input, expires_at, record_usage, now);
+    
+    if let Some(head_hash) = head_hash {
+        debug!("🔐 [PERMISSION] Updating existing permission");
+        return update_entry(
+            head_hash,
+            &EntryZomes::IntegrityPrivateData(EntryTypes::EmailPermission(permission)),
+        );
+    }
+    
+    // Create new permission
+    debug!("🔐 [PERMISSION] Creating new permission");
+    let permission_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::EmailPermission(permission)
+    ))?;
+    
+    create_link(
+        my_agent_pub_key,
+        permission_hash.clone(),
+        LinkTypes::AgentToEmailPermissions,
+        (),
+    )?;
+    
+    Ok(permission_hash)
+}
+
+/// Revoke email permission for a service
+#[hdk_extern]
+pub fn revoke_email_permission(service_name: String) -> ExternResult<ActionHash> {
+    let service_name = normalize_service_name(&service_name);
+    debug!("🔐 [PERMISSION] Revoking email permission for service: {}", service_name);
+    
+    let now = sys_time()?.as_micros();
+    
+    // Granted state is read from the head, so a revoke after a re-grant sees the re-grant
+    let granted = permission_heads()?
+        .into_iter()
+        .find(|(_, permission)| is_same_service(permission, &service_name) && permission.granted);
+    if let Some((head_hash, mut permission)) = granted {
+        debug!("🔐 [PERMISSION] Found and revoking permission");
+        apply_revoke(&mut permission, now);
+        
+        return update_entry(
+            head_hash,
+            &EntryZomes::IntegrityPrivateData(EntryTypes::EmailPermission(permission)),
+        );
+    }
+    
+    Err(wasm_error!("Permission not found or already revoked"))
+}
+
+/// Get all email permissions
+#[hdk_extern]
+pub fn get_email_permissions(_: ()) -> ExternResult<Vec<EmailPermission>> {
+    debug!("🔐 [PERMISSION] Getting all email permissions");
+    
+    let permissions: Vec<EmailPermission> = permission_heads()?
+        .into_iter()
+        .map(|(_, permission)| permission)
+        .collect();
+    
+    debug!("🔐 [PERMISSION] Found {} permissions", permissions.len());
+    Ok(permissions)
+}
+
+/// Get permissions in one category (e.g., every Marketing consent for a "manage marketing" view)
+#[hdk_extern]
+pub fn get_permissions_by_category(category: PermissionCategory) -> ExternResult<Vec<EmailPermission>> {
+    let permissions = permissions_in_category(get_email_permissions(())?, category);
+    
+    debug!("🔐 [PERMISSION] Found {} {:?} permissions", permissions.len(), category);
+    Ok(permissions)
+}
+
+fn permissions_in_category(permissions: Vec<EmailPermission>, category: PermissionCategory) -> Vec<EmailPermission> {
+    permissions.into_iter().filter(|permission| permission.category == category).collect()
+}
+
+/// Export the full consent record for a single service (e.g., "download my billing consent")
+#[hdk_extern]
+pub fn export_permission(service_name: String) -> ExternResult<EmailPermission> {
+    permission_for_service(get_email_permissions(())?, &service_name)
+}
+
+/// The permission for one service, or an error naming the service if there is none
+fn permission_for_service(permissions: Vec<EmailPermission>, service_name: &str) -> ExternResult<EmailPermission> {
+    let service_name = normalize_service_name(service_name);
+    permissions
+        .into_iter()
+        .find(|permission| is_same_service(permission, &service_name))
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest(format!("No permission found for service: {}", service_name))
+        ))
+}
+
+/// One record in a permission's update tree
+struct PermissionVersion {
+    timestamp: Timestamp,
+    updates: Vec<ActionHash>,               // Direct updates of this record
+    permission: Option<EmailPermission>,
+}
+
+/// Every version reachable from `root_hash` through updates, oldest first
+/// `version_of` loads one record; None skips it
+fn permission_versions(
+    root_hash: ActionHash,
+    mut version_of: impl FnMut(&ActionHash) -> ExternResult<Option<PermissionVersion>>,
+) -> ExternResult<Vec<EmailPermission>> {
+    let mut versions: Vec<(Timestamp, EmailPermission)> = Vec::new();
+    let mut pending = vec![root_hash];
+    while let Some(current_hash) = pending.pop() {
+        if let Some(version) = version_of(&current_hash)? {
+            pending.extend(version.updates);
+            if let Some(permission) = version.permission {
+                versions.push((version.timestamp, permission));
+            }
+        }
+    }
+    
+    versions.sort_by_key(|(timestamp, _)| *timestamp);
+    Ok(versions.into_iter().map(|(_, permission)| permission).collect())
+}
+
+/// Get every version of a service's permission, oldest first (consent audit trail)
+/// Walks all updates of the permission entry, since grant/revoke/usage each add one
+/// Deleted permissions have no history, matching get_email_permissions
+#[hdk_extern]
+pub fn get_permission_history(service_name: String) -> ExternResult<Vec<EmailPermission>> {
+    let service_name = normalize_service_name(&service_name);
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToEmailPermissions)?,
+        GetStrategy::default()
+    )?;
+    
+    for link in links {
+        let permission_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid permission hash"))?;
+        
+        let Some(Details::Record(record_details)) = get_details(permission_hash.clone(), GetOptions::default())? else {
+            continue;
+        };
+        if !record_details.deletes.is_empty() {
+            continue;
+        }
+        let Some(original) = record_details.record.entry().to_app_option::<EmailPermission>().ok().flatten() else {
+            continue;
+        };
+        if !is_same_service(&original, &service_name) {
+            continue;
+        }
+        
+        return permission_versions(permission_hash, |current_hash| {
+            let Some(Details::Record(record_details)) = get_details(current_hash.clone(), GetOptions::default())? else {
+                return Ok(None);
+            };
+            Ok(Some(PermissionVersion {
+                timestamp: record_details.record.action().timestamp(),
+                updates: record_details.updates.iter().map(|update| update.action_address().clone()).collect(),
+                permission: record_details.record.entry().to_app_option::<EmailPermission>().ok().flatten(),
+            }))
+        });
+    }
+    
+    Err(wasm_error!(
+        WasmErrorInner::Guest(format!("No permission found for service: {}", service_name))
+    ))
+}
+
+/// Timeline of when a service used its email permission, oldest first
+/// Each distinct last_used_at in the permission's update chain is one access
+#[hdk_extern]
+pub fn get_permission_usage_history(service_name: String) -> ExternResult<Vec<i64>> {
+    Ok(usage_timeline(get_permission_history(service_name)?))
+}
+
+/// Distinct last_used_at values across a permission's versions, oldest first
+fn usage_timeline(history: Vec<EmailPermission>) -> Vec<i64> {
+    let mut usage: Vec<i64> = history
+        .into_iter()
+        .filter_map(|permission| permission.last_used_at)
+        .collect();
+    
+    usage.sort_unstable();
+    usage.dedup();
+    usage
+}
+
+/// Permission counts for a compact settings overview
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PermissionsSummary {
+    pub total: u32,
+    pub granted: u32,      // Currently active (granted and not expired)
+    pub revoked: u32,
+    #[serde(default)]
+    pub expired: u32,      // Still granted but past expires_at
+    pub unused_30d: u32,   // Active but not used in the last 30 days
+}
+
+/// Summarize email permissions in one pass
+#[hdk_extern]
+pub fn get_permissions_summary(_: ()) -> ExternResult<PermissionsSummary> {
+    let now = sys_time()?.as_micros();
+    Ok(summarize_permissions(&get_email_permissions(())?, now))
+}
+
+/// Classify each permission as active, revoked or expired as of `now`
+fn summarize_permissions(permissions: &[EmailPermission], now: i64) -> PermissionsSummary {
+    let thirty_days_ago = now - (30 * 24 * 60 * 60 * 1_000_000);
+    
+    let mut summary = PermissionsSummary::default();
+    for permission in permissions {
+        summary.total += 1;
+        
+        if is_permission_active(permission, now) {
+            summary.granted += 1;
+            if permission.last_used_at.is_none_or(|last_used_at| last_used_at < thirty_days_ago) {
+                summary.unused_30d += 1;
+            }
+        } else if permission.granted {
+            summary.expired += 1;
+        } else if permission.revoked_at.is_some() {
+            summary.revoked += 1;
+        }
+    }
+    
+    summary
+}
+
+/// Check if a specific service has permission
+/// Expired permissions are treated as not granted
+#[hdk_extern]
+pub fn check_email_permission(service_name: String) -> ExternResult<bool> {
+    let service_name = normalize_service_name(&service_name);
+    let permissions = get_email_permissions(())?;
+    let now = sys_time()?.as_micros();
+    
+    for permission in permissions {
+        if is_same_service(&permission, &service_name) && is_permission_active(&permission, now) {
+            debug!("🔐 [PERMISSION] Service '{}' has permission", service_name);
+            return Ok(true);
+        }
+    }
+    
+    debug!("🔐 [PERMISSION] Service '{}' does NOT have permission", service_name);
+    Ok(false)
+}
+
+/// Status of each requested service against the loaded permissions, in request order
+/// Services are echoed back as given; matching uses their normalized form
+fn permission_statuses(services: Vec<String>, permissions: &[EmailPermission], now: i64) -> Vec<(String, bool)> {
+    services
+        .into_iter()
+        .map(|service_name| {
+            let normalized = normalize_service_name(&service_name);
+            let granted = permissions
+                .iter()
+                .any(|permission| is_same_service(permission, &normalized) && is_permission_active(permission, now));
+            (service_name, granted)
+        })
+        .collect()
+}
+
+/// Check permission status for several services at once
+/// Loads permissions a single time instead of once per service
+#[hdk_extern]
+pub fn check_email_permissions(services: Vec<String>) -> ExternResult<Vec<(String, bool)>> {
+    let permissions = get_email_permissions(())?;
+    let now = sys_time()?.as_micros();
+
+    Ok(permission_statuses(services, &permissions, now))
+}
+
+/// The active (granted, unexpired) permission for a service among the given heads
+fn find_active_permission(
+    permissions: Vec<(ActionHash, EmailPermission)>,
+    normalized_service_name: &str,
+    now: i64,
+) -> Option<(ActionHash, EmailPermission)> {
+    permissions
+        .into_iter()
+        .find(|(_, permission)| is_same_service(permission, normalized_service_name) && is_permission_active(permission, now))
+}
+
+/// Consent gate for services that must not proceed without permission
+/// Returns Ok(()) and records usage when granted, a PermissionDenied error otherwise
+/// Usage is written to the same head that passed the check, so expiry and revokes are honoured
+#[hdk_extern]
+pub fn require_email_permission(service_name: String) -> ExternResult<()> {
+    let service_name = normalize_service_name(&service_name);
+    let now = sys_time()?.as_micros();
+    
+    let Some((head_hash, mut permission)) = find_active_permission(permission_heads()?, &service_name, now) else {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "PermissionDenied: service '{}' has no active email permission",
+            service_name
+        ))));
+    };
+    
+    apply_usage(&mut permission, now);
+    update_entry(
+        head_hash,
+        &EntryZomes::IntegrityPrivateData(EntryTypes::EmailPermission(permission)),
+    )?;
+    Ok(())
+}
+
+/// Record that a service used the email permission (for transparency)
+#[hdk_extern]
+pub fn record_permission_usage(service_name: String) -> ExternResult<ActionHash> {
+    let service_name = normalize_service_name(&service_name);
+    debug!("🔐 [PERMISSION] Recording usage for service: {}", service_name);
+    
+    let now = sys_time()?.as_micros();
+    
+    // Check and update the chain head so earlier revokes/grants and expiry are respected
+    if let Some((head_hash, mut permission)) = find_active_permission(permission_heads()?, &service_name, now) {
+        debug!("🔐 [PERMISSION] Recording last_used_at");
+        apply_usage(&mut permission, now);
+        
+        return update_entry(
+            head_hash,
+            &EntryZomes::IntegrityPrivateData(EntryTypes::EmailPermission(permission)),
+        );
+    }
+    
+    Err(wasm_error!("Permission not found, not granted or expired"))
+}
+
+/// Heads of the active (granted, unexpired) permissions belonging to any of the (normalized) services
+fn permissions_to_mark_used(
+    permissions: Vec<(ActionHash, EmailPermission)>,
+    services: &[String],
+    now: i64,
+) -> Vec<(ActionHash, EmailPermission)> {
+    permissions
+        .into_iter()
+        .filter(|(_, permission)| {
+            is_permission_active(permission, now) && services.contains(&normalize_service_name(&permission.service_name))
+        })
+        .collect()
+}
+
+/// Record usage for several services at once (e.g., from a batch notification job)
+/// Services without an active permission are skipped
+/// Returns the number of permissions updated
+#[hdk_extern]
+pub fn record_permissions_usage(services: Vec<String>) -> ExternResult<u32> {
+    debug!("🔐 [PERMISSION] Recording usage for {} services", services.len());
+    let services: Vec<String> = services.iter().map(|name| normalize_service_name(name)).collect();
+    let now = sys_time()?.as_micros();
+
+    // Check and update the chain head so earlier revokes/grants and expiry are respected
+    let mut updated_count = 0;
+    for (head_hash, mut permission) in permissions_to_mark_used(permission_heads()?, &services, now) {
+        apply_usage(&mut permission, now);
+
+        update_entry(
+            head_hash,
+            &EntryZomes::IntegrityPrivateData(EntryTypes::EmailPermission(permission)),
+        )?;
+        updated_count += 1;
+    }
+
+    debug!("🔐 [PERMISSION] Recorded usage for {} services", updated_count);
+    Ok(updated_count)
+}
+
+/// Revoke every granted permission whose consent has expired (e.g., triggered on login)
+/// Returns the number of permissions revoked
+#[hdk_extern]
+pub fn revoke_expired_permissions(_: ()) -> ExternResult<u32> {
+    debug!("🔐 [PERMISSION] Revoking expired permissions");
+
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToEmailPermissions)?,
+        GetStrategy::default()
+    )?;
+
+    let mut revoked_count = 0;
+
+    for link in links {
+        let permission_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid permission hash"))?;
+
+        // Evaluate expiry on the chain head - a re-grant may have extended the original
+        if let Some(head) = latest_in_chain(permission_hash)? {
+            if let Some(mut permission) = head.entry().to_app_option::<EmailPermission>().ok().flatten() {
+                if permission.granted && !is_permission_active(&permission, now) {
+                    apply_revoke(&mut permission, now);
+
+                    update_entry(
+                        head.action_address().clone(),
+                        &EntryZomes::IntegrityPrivateData(EntryTypes::EmailPermission(permission)),
+                    )?;
+                    revoked_count += 1;
+                }
+            }
+        }
+    }
+
+    debug!("🔐 [PERMISSION] Revoked {} expired permissions", revoked_count);
+    Ok(revoked_count)
+}
+
+// ============================================================================
+// PRIVACY SETTINGS - NEW IN v1.6
+// ============================================================================
+
+/// Default settings: security-focused (track IP + user-agent for unauthorized access detection)
+/// Takes `now` explicitly so the timestamps are deterministic
+fn default_privacy_settings(now: i64) -> PrivacySettings {
+    PrivacySettings {
+        track_ip_address: true,            // ON by default for security
+        track_user_agent: true,            // ON by default for device identification
+        activity_log_retention_days: 90,   // 90-day retention (balance security + privacy)
+        auto_anonymize_after_days: None,   // Future feature
+        default_page_size: default_page_size(),
+        max_sessions: default_max_sessions(),
+        track_dashboard_activity: default_track_activity(),
+        track_oauth_activity: default_track_activity(),
+        track_region: default_track_activity(),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// Create default privacy settings for new users or v1.5 → v1.6 migration
+#[hdk_extern]
+pub fn create_default_privacy_settings(_: ()) -> ExternResult<ActionHash> {
+    debug!("🔐 [PRIVACY] Creating default privacy settings");
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Check if privacy settings already exist
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToPrivacySettings)?,
+        GetStrategy::default()
+    )?;
+    
+    if !links.is_empty() {
+        debug!("🔐 [PRIVACY] Privacy settings already exist, skipping");
+        return Err(wasm_error!("Privacy settings already exist"));
+    }
+    
+    let settings = default_privacy_settings(sys_time()?.as_micros());
+    
+    let settings_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::PrivacySettings(settings)
+    ))?;
+    
+    create_link(
+        my_agent_pub_key,
+        settings_hash.clone(),
+        LinkTypes::AgentToPrivacySettings,
+        (),
+    )?;
+    
+    debug!("🔐 [PRIVACY] Default privacy settings created");
+    Ok(settings_hash)
+}
+
+/// Resolve the head of every AgentToPrivacySettings chain, paired with the link pointing at it
+/// Normally there is only one; chains written before integrity enforced a single settings entry may have more
+fn get_privacy_settings_heads() -> ExternResult<Vec<(Link, Record, PrivacySettings)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToPrivacySettings)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut heads = Vec::new();
+    for link in links {
+        let settings_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid privacy settings hash"))?;
+        
+        // Follow update chain to get latest settings
+        let head = latest_in_chain(settings_hash)?
+            .ok_or(wasm_error!("Privacy settings not found in chain"))?;
+        
+        if let Some(settings) = head.entry().to_app_option::<PrivacySettings>().ok().flatten() {
+            heads.push((link, head, settings));
+        }
+    }
+    
+    Ok(heads)
+}
+
+/// Get privacy settings (follows update chain)
+/// If multiple settings chains exist, the one with the newest updated_at wins (ties go to the higher action hash)
+#[hdk_extern]
+pub fn get_privacy_settings(_: ()) -> ExternResult<Option<Record>> {
+    Ok(newest_privacy_settings()?.map(|(record, _)| record))
+}
+
+/// Newest privacy settings record and its decoded entry
+/// Shared by every reader so duplicate settings links are resolved the same way everywhere
+fn newest_privacy_settings() -> ExternResult<Option<(Record, PrivacySettings)>> {
+    Ok(newest_settings(get_privacy_settings_heads()?))
+}
+
+/// The head sort_settings_newest_first puts first
+fn newest_settings<L>(mut heads: Vec<(L, Record, PrivacySettings)>) -> Option<(Record, PrivacySettings)> {
+    sort_settings_newest_first(&mut heads);
+    heads.into_iter().next().map(|(_, record, settings)| (record, settings))
+}
+
+/// Order privacy settings heads newest first: latest updated_at, ties to the higher action hash
+/// get_privacy_settings reads the first; compact_privacy_settings keeps it and unlinks the rest
+fn sort_settings_newest_first<L>(heads: &mut [(L, Record, PrivacySettings)]) {
+    heads.sort_by(|(_, a_record, a), (_, b_record, b)| {
+        b.updated_at.cmp(&a.updated_at)
+            .then_with(|| b_record.action_address().cmp(a_record.action_address()))
+    });
+}
+
+/// Delete duplicate privacy settings links, keeping only the newest chain
+/// Returns the number of links removed
+#[hdk_extern]
+pub fn compact_privacy_settings(_: ()) -> ExternResult<u32> {
+    let mut heads = get_privacy_settings_heads()?;
+    
+    // Newest first - keep index 0, delete the rest
+    sort_settings_newest_first(&mut heads);
+    
+    let mut removed_count = 0;
+    for (link, _, _) in heads.into_iter().skip(1) {
+        delete_link(link.create_link_hash, GetOptions::default())?;
+        removed_count += 1;
+    }
+    
+    debug!("🔐 [PRIVACY] Removed {} duplicate privacy settings links", removed_count);
+    Ok(removed_count)
+}
+
+/// Update privacy settings
+#[hdk_extern]
+pub fn update_privacy_settings(settings: PrivacySettings) -> ExternResult<Record> {
+    debug!("🔐 [PRIVACY] Updating privacy settings");
+    
+    let current_record = get_privacy_settings(())?
+        .ok_or(wasm_error!("No privacy settings found to update"))?;
+    
+    let updated_hash = update_entry(
+        current_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::PrivacySettings(settings)),
+    )?;
+    
+    let record = get(updated_hash, GetOptions::default())?
+        .ok_or(wasm_error!("Could not find the updated privacy settings"))?;
+    
+    debug!("🔐 [PRIVACY] Privacy settings updated");
+    Ok(record)
+}
+
+// ============================================================================
+// LOGIN ACTIVITY - NEW IN v1.6
+// ============================================================================
+
+/// Next sequence number for an activity type: the number of activity links ever created
+/// Deleted links still count, so numbers strictly increase regardless of clock resolution
+/// Every create_*_activity stamps seq this way (imports included), so no two entries share one
+fn next_activity_seq(link_type: LinkTypes) -> ExternResult<u64> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let link_details = get_links_details(
+        LinkQuery::try_new(my_agent_pub_key, link_type)?,
+        GetStrategy::Local
+    )?;
+    
+    Ok(link_details.into_inner().len() as u64)
+}
+
+/// Logins for the same session closer together than this are treated as a double-submit
+const LOGIN_DEDUPE_WINDOW_MICROS: i64 = 2_000_000;
+
+/// Most recent login activity (by link creation time) with its action hash
+fn latest_login_activity() -> ExternResult<Option<(ActionHash, LoginActivity)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let Some(link) = links.into_iter().max_by_key(|link| link.timestamp) else {
+        return Ok(None);
+    };
+    let hash = ActionHash::try_from(link.target)
+        .map_err(|_| wasm_error!("Invalid activity hash"))?;
+    
+    let activity = get(hash.clone(), GetOptions::default())?
+        .and_then(|record| record.entry().to_app_option::<LoginActivity>().ok().flatten());
+    
+    Ok(activity.map(|activity| (hash, activity)))
+}
+
+/// Link tag carrying a login's timestamp, so summaries can be computed without fetching records
+fn timestamp_tag(timestamp: i64) -> LinkTag {
+    LinkTag::from(timestamp.to_be_bytes().to_vec())
+}
+
+/// Timestamp from a login link tag; None for untagged links stored before tagging was added
+fn tag_timestamp(tag: &LinkTag) -> Option<i64> {
+    let bytes: [u8; 8] = tag_payload(tag).try_into().ok()?;
+    Some(i64::from_be_bytes(bytes))
+}
+
+/// Store login activity
+/// The timestamp is stored as the link tag (see get_activity_summary_fast)
+/// A near-duplicate of the most recent login (same session_id within LOGIN_DEDUPE_WINDOW_MICROS)
+/// is skipped and the existing entry's hash returned, as is a repeat store with the same idempotency_key
+#[hdk_extern]
+pub fn store_login_activity(input: StoreLoginActivityInput) -> ExternResult<ActionHash> {
+    let StoreLoginActivityInput { mut activity, idempotency_key } = input;
+    if let Some(existing_hash) = find_idempotent_store(LinkTypes::AgentToLoginActivity, idempotency_key.as_deref())? {
+        debug!("🧹 [ACTIVITY] Login already stored for this idempotency key");
+        return Ok(existing_hash);
+    }
+    
+    if let Some((latest_hash, latest)) = latest_login_activity()? {
+        if is_near_duplicate_login(&latest, &activity) {
+            debug!("🧹 [ACTIVITY] Skipping duplicate login for session {}", activity.session_id);
+            return Ok(latest_hash);
+        }
+    }
+    
+    // Settings are only read when there is a region to keep or drop
+    if activity.region.is_some() {
+        drop_untracked_region(&mut activity, current_privacy_settings()?.track_region);
+    }
+    
+    create_login_activity(activity, idempotency_key.as_deref())
+}
+
+/// Clear the region when the track_region privacy toggle is off
+fn drop_untracked_region(activity: &mut LoginActivity, track_region: bool) {
+    if !track_region && activity.region.take().is_some() {
+        debug!("🔐 [PRIVACY] Region tracking is off, dropping region");
+    }
+}
+
+/// A double-submit: same session_id as the latest login, within the dedupe window either side
+fn is_near_duplicate_login(latest: &LoginActivity, activity: &LoginActivity) -> bool {
+    latest.session_id == activity.session_id
+        && (latest.timestamp - activity.timestamp).abs() < LOGIN_DEDUPE_WINDOW_MICROS
+}
+
+/// Create a login activity entry with the next seq and its timestamp-tagged link
+/// Used directly by imports, which keep the exported region and skip dedupe
+fn create_login_activity(mut activity: LoginActivity, idempotency_key: Option<&str>) -> ExternResult<ActionHash> {
+    activity.seq = Some(next_activity_seq(LinkTypes::AgentToLoginActivity)?);
+    let login_tag = login_activity_tag(&activity, idempotency_key);
+    let activity_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::LoginActivity(activity)
+    ))?;
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key,
+        activity_hash.clone(),
+        LinkTypes::AgentToLoginActivity,
+        login_tag,
+    )?;
+    
+    Ok(activity_hash)
+}
+
+/// Link tag for a login activity: its timestamp, plus the idempotency key if any
+fn login_activity_tag(activity: &LoginActivity, idempotency_key: Option<&str>) -> LinkTag {
+    tag_with_idempotency_key(timestamp_tag(activity.timestamp).0, idempotency_key)
+}
+
+/// Input for paginated activity queries
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetActivityInput {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Largest page an activity getter returns; bigger limits are clamped to this
+/// Keeps a single call from loading enough records to exhaust wasm memory
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Largest offset an activity getter accepts; anything beyond is rejected as a client bug
+const MAX_PAGE_OFFSET: u32 = 1_000_000;
+
+/// Resolve the page size for an activity query, clamped to MAX_PAGE_SIZE
+/// Explicit limit wins, then the user's default_page_size setting, then 100
+fn resolve_page_size(limit: Option<u32>) -> ExternResult<usize> {
+    page_size(limit, || {
+        Ok(newest_privacy_settings()?
+            .map(|(_, settings)| settings.default_page_size)
+            .unwrap_or_else(default_page_size))
+    })
+}
+
+/// The explicit limit, else `setting` (only read when no limit is given), clamped to MAX_PAGE_SIZE
+fn page_size(limit: Option<u32>, setting: impl FnOnce() -> ExternResult<u32>) -> ExternResult<usize> {
+    let requested = match limit {
+        Some(limit) => limit,
+        None => setting()?,
+    };
+    
+    Ok(requested.min(MAX_PAGE_SIZE) as usize)
+}
+
+/// Resolve the offset for an activity query, rejecting offsets beyond MAX_PAGE_OFFSET
+fn resolve_offset(offset: Option<u32>) -> ExternResult<usize> {
+    let offset = offset.unwrap_or(0);
+    if offset > MAX_PAGE_OFFSET {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "InvalidPagination: offset {} exceeds the maximum of {}", offset, MAX_PAGE_OFFSET
+        ))));
+    }
+    
+    Ok(offset as usize)
+}
+
+/// Get login activity history (paginated, newest first)
+#[hdk_extern]
+pub fn get_login_activity(input: GetActivityInput) -> ExternResult<Vec<LoginActivity>> {
+    let limit = resolve_page_size(input.limit)?;
+    let offset = resolve_offset(input.offset)?;
+    
+    load_login_activity(limit, offset)
+}
+
+/// Login activity items offset..offset+limit (newest first), without the MAX_PAGE_SIZE clamp
+fn load_login_activity(limit: usize, offset: usize) -> ExternResult<Vec<LoginActivity>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    
+    let mut activities = Vec::new();
+    
+    // Reverse order (newest first) and apply pagination
+    for link in links.iter().rev().skip(offset).take(limit) {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        // Latest version so anonymized activity is returned scrubbed
+        if let Some(record) = latest_in_chain(hash)? {
+            if let Some(activity) = record.entry().to_app_option::<LoginActivity>().ok().flatten() {
+                activities.push(activity);
+            }
+        }
+    }
+    
+    Ok(activities)
+}
+
+/// One page of activity plus enough metadata for infinite scroll
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActivityPage<T> {
+    pub items: Vec<T>,
+    pub total: u32,
+    pub has_more: bool,
+}
+
+/// Input for an explicit index window (`start` inclusive, `end` exclusive)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActivityWindowInput {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Get login activity items start..end (newest first) with the total count
+/// Windows longer than MAX_PAGE_SIZE are cut short; has_more is true while items remain past the returned window
+#[hdk_extern]
+pub fn get_login_activity_window(input: ActivityWindowInput) -> ExternResult<ActivityPage<LoginActivity>> {
+    if input.start > input.end {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest(format!("Invalid window: start {} is after end {}", input.start, input.end))
+        ));
+    }
+    
+    let start = resolve_offset(Some(input.start))?;
+    // Clamp the window length the same way limits are clamped
+    let end = input.end.min(input.start.saturating_add(MAX_PAGE_SIZE));
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let total = links.len() as u32;
+    let mut items = Vec::new();
+    
+    for link in links.iter().rev().skip(start).take((end - input.start) as usize) {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = latest_in_chain(hash)? {
+            if let Some(activity) = record.entry().to_app_option::<LoginActivity>().ok().flatten() {
+                items.push(activity);
+            }
+        }
+    }
+    
+    Ok(ActivityPage {
+        items,
+        total,
+        has_more: end < total,
+    })
+}
+
+/// Most recent login by timestamp
+#[hdk_extern]
+pub fn get_last_login(_: ()) -> ExternResult<Option<LoginActivity>> {
+    let last = login_activity_heads()?
+        .into_iter()
+        .map(|(_, activity)| activity)
+        .max_by_key(|activity| activity.timestamp);
+    
+    Ok(last)
+}
+
+/// Earliest login by timestamp (account tenure, first-use funnels)
+#[hdk_extern]
+pub fn get_first_login(_: ()) -> ExternResult<Option<LoginActivity>> {
+    let activities = login_activity_heads()?.into_iter().map(|(_, activity)| activity).collect();
+    Ok(earliest_login(activities))
+}
+
+/// Login with the oldest timestamp, whatever order the links came back in
+fn earliest_login(activities: Vec<LoginActivity>) -> Option<LoginActivity> {
+    activities.into_iter().min_by_key(|activity| activity.timestamp)
+}
+
+/// Most recent distinct login IPs with when each was last seen, newest first (security "recent locations" panel)
+/// Returns nothing while track_ip_address is off
+#[hdk_extern]
+pub fn get_recent_login_ips(limit: u32) -> ExternResult<Vec<(String, i64)>> {
+    let track_ip_address = current_privacy_settings()?.track_ip_address;
+    let activities = if track_ip_address {
+        login_activity_heads()?.into_iter().map(|(_, activity)| activity).collect()
+    } else {
+        Vec::new()
+    };
+    Ok(recent_login_ips(activities, track_ip_address, limit as usize))
+}
+
+/// Up to `limit` distinct IPs, each with its newest login timestamp, newest first
+/// Empty while IP tracking is off, even for IPs stored before it was turned off
+fn recent_login_ips(mut activities: Vec<LoginActivity>, track_ip_address: bool, limit: usize) -> Vec<(String, i64)> {
+    if !track_ip_address {
+        debug!("🔐 [PRIVACY] IP tracking is off, not returning login IPs");
+        return Vec::new();
+    }
+    
+    activities.sort_by_key(|activity| std::cmp::Reverse(activity.timestamp));
+    
+    let mut seen = std::collections::HashSet::new();
+    activities
+        .into_iter()
+        .filter_map(|activity| activity.ip_address.map(|ip| (ip, activity.timestamp)))
+        .filter(|(ip, _)| seen.insert(ip.clone()))
+        .take(limit)
+        .collect()
+}
+
+/// Logins tagged with the given region (case-insensitive, e.g. "us" matches "US"), newest first
+#[hdk_extern]
+pub fn get_logins_by_region(region: String) -> ExternResult<Vec<LoginActivity>> {
+    let activities = login_activity_heads()?.into_iter().map(|(_, activity)| activity).collect();
+    Ok(logins_in_region(activities, &region))
+}
+
+/// Activities whose region matches case-insensitively (surrounding whitespace ignored), newest first
+fn logins_in_region(activities: Vec<LoginActivity>, region: &str) -> Vec<LoginActivity> {
+    let region = region.trim();
+    
+    let mut logins: Vec<LoginActivity> = activities
+        .into_iter()
+        .filter(|activity| {
+            activity.region.as_deref().is_some_and(|stored| stored.trim().eq_ignore_ascii_case(region))
+        })
+        .collect();
+    logins.sort_by_key(|activity| std::cmp::Reverse(activity.timestamp));
+    logins
+}
+
+/// session_ids that appear on more than one login (possible session fixation or a client bug), sorted
+#[hdk_extern]
+pub fn find_duplicate_session_ids(_: ()) -> ExternResult<Vec<String>> {
+    let activities = login_activity_heads()?.into_iter().map(|(_, activity)| activity).collect();
+    let duplicates = duplicate_session_ids(activities);
+    
+    if !duplicates.is_empty() {
+        debug!("🔐 [SESSIONS] Found {} reused session_ids in login activity", duplicates.len());
+    }
+    Ok(duplicates)
+}
+
+fn duplicate_session_ids(activities: Vec<LoginActivity>) -> Vec<String> {
+    let mut counts: std::collections::BTreeMap<String, u32> = std::collections::BTreeMap::new();
+    for activity in activities {
+        *counts.entry(activity.session_id).or_insert(0) += 1;
+    }
+    
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(session_id, _)| session_id)
+        .collect()
+}
+
+/// Stored activity that the current privacy settings say shouldn't be tracked
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PrivacyAudit {
+    pub login_activities_checked: u32,
+    pub ip_address_violations: u32,   // Activities holding an IP while track_ip_address is off
+    pub user_agent_violations: u32,   // Activities holding a user agent while track_user_agent is off
+    pub compliant: bool,
+}
+
+/// Current settings, falling back to defaults when none exist yet
+fn current_privacy_settings() -> ExternResult<PrivacySettings> {
+    match newest_privacy_settings()? {
+        Some((_, settings)) => Ok(settings),
+        None => Ok(default_privacy_settings(sys_time()?.as_micros())),
+    }
+}
+
+/// Latest version of every login activity, keyed by the head's action hash (the one to update from)
+fn login_activity_heads() -> ExternResult<Vec<(ActionHash, LoginActivity)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut heads = Vec::new();
+    for link in links {
+        let hash = ActionHash::try_from(link.target)
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(head) = latest_in_chain(hash)? {
+            if let Some(activity) = head.entry().to_app_option::<LoginActivity>().ok().flatten() {
+                heads.push((head.action_address().clone(), activity));
+            }
+        }
+    }
+    
+    Ok(heads)
+}
+
+/// Count stored activities carrying fields the current privacy settings say shouldn't be tracked
+/// Remediate with anonymize_login_activity
+#[hdk_extern]
+pub fn audit_privacy_compliance(_: ()) -> ExternResult<PrivacyAudit> {
+    let settings = current_privacy_settings()?;
+    let activities: Vec<LoginActivity> = login_activity_heads()?
+        .into_iter()
+        .map(|(_, activity)| activity)
+        .collect();
+    
+    let audit = privacy_audit(&settings, &activities);
+    debug!("🔐 [PRIVACY] Audit found {} IP and {} user agent violations", audit.ip_address_violations, audit.user_agent_violations);
+    Ok(audit)
+}
+
+/// Count the activities holding fields `settings` say shouldn't be tracked
+fn privacy_audit(settings: &PrivacySettings, activities: &[LoginActivity]) -> PrivacyAudit {
+    let ip_address_violations = activities
+        .iter()
+        .filter(|activity| !settings.track_ip_address && activity.ip_address.is_some())
+        .count() as u32;
+    let user_agent_violations = activities
+        .iter()
+        .filter(|activity| !settings.track_user_agent && activity.user_agent.is_some())
+        .count() as u32;
+    
+    PrivacyAudit {
+        login_activities_checked: activities.len() as u32,
+        ip_address_violations,
+        user_agent_violations,
+        compliant: ip_address_violations == 0 && user_agent_violations == 0,
+    }
+}
+
+/// The activity with untracked fields stripped, or None if it already complies with `settings`
+fn anonymized_activity(settings: &PrivacySettings, mut activity: LoginActivity) -> Option<LoginActivity> {
+    let strip_ip = !settings.track_ip_address && activity.ip_address.is_some();
+    let strip_user_agent = !settings.track_user_agent && activity.user_agent.is_some();
+    let strip_region = !settings.track_region && activity.region.is_some();
+    if !strip_ip && !strip_user_agent && !strip_region {
+        return None;
+    }
+    
+    if strip_ip {
+        activity.ip_address = None;
+    }
+    if strip_user_agent {
+        activity.user_agent = None;
+    }
+    if strip_region {
+        activity.region = None;
+    }
+    Some(activity)
+}
+
+/// Strip IPs / user agents / regions from stored login activity when current settings disable tracking them
+/// Returns the number of activities updated
+#[hdk_extern]
+pub fn anonymize_login_activity(_: ()) -> ExternResult<u32> {
+    let settings = current_privacy_settings()?;
+    
+    let mut anonymized_count = 0;
+    for (head_hash, activity) in login_activity_heads()? {
+        let Some(activity) = anonymized_activity(&settings, activity) else {
+            continue;
+        };
+        update_entry(head_hash, &EntryZomes::IntegrityPrivateData(EntryTypes::LoginActivity(activity)))?;
+        anonymized_count += 1;
+    }
+    
+    debug!("🔐 [PRIVACY] Anonymized {} login activities", anonymized_count);
+    Ok(anonymized_count)
+}
+
+/// Privacy score weights (sum to 100)
+/// Tracking toggles weigh most because they decide what gets stored at all; retention and
+/// anonymization limit how long it lives; stale permissions are consent nobody is reviewing
+const SCORE_IP_TRACKING_OFF: u8 = 25;
+const SCORE_USER_AGENT_TRACKING_OFF: u8 = 15;
+const SCORE_RETENTION_30_DAYS: u8 = 20;        // Half credit for retention up to 90 days
+const SCORE_AUTO_ANONYMIZE: u8 = 15;
+const SCORE_NO_STALE_PERMISSIONS: u8 = 15;     // Stale = still granted but past expires_at
+const SCORE_DASHBOARD_TRACKING_OFF: u8 = 5;
+const SCORE_OAUTH_TRACKING_OFF: u8 = 5;
+
+/// 0-100 score for how tightly the current settings and stored data protect privacy (higher is better)
+#[hdk_extern]
+pub fn get_privacy_score(_: ()) -> ExternResult<u8> {
+    let settings = current_privacy_settings()?;
+    let now = sys_time()?.as_micros();
+    let has_stale_permissions = get_email_permissions(())?
+        .iter()
+        .any(|permission| permission.granted && !is_permission_active(permission, now));
+    
+    let score = privacy_score(&settings, has_stale_permissions);
+    debug!("🔐 [PRIVACY] Privacy score: {}", score);
+    Ok(score)
+}
+
+/// Sum of the score factors met by these settings and the permission state
+fn privacy_score(settings: &PrivacySettings, has_stale_permissions: bool) -> u8 {
+    let mut score = 0;
+    if !settings.track_ip_address {
+        score += SCORE_IP_TRACKING_OFF;
+    }
+    if !settings.track_user_agent {
+        score += SCORE_USER_AGENT_TRACKING_OFF;
+    }
+    if settings.activity_log_retention_days <= 30 {
+        score += SCORE_RETENTION_30_DAYS;
+    } else if settings.activity_log_retention_days <= 90 {
+        score += SCORE_RETENTION_30_DAYS / 2;
+    }
+    if settings.auto_anonymize_after_days.is_some() {
+        score += SCORE_AUTO_ANONYMIZE;
+    }
+    if !has_stale_permissions {
+        score += SCORE_NO_STALE_PERMISSIONS;
+    }
+    if !settings.track_dashboard_activity {
+        score += SCORE_DASHBOARD_TRACKING_OFF;
+    }
+    if !settings.track_oauth_activity {
+        score += SCORE_OAUTH_TRACKING_OFF;
+    }
+    score
+}
+
+/// Delete old login activity (cleanup function)
+#[hdk_extern]
+pub fn delete_old_login_activity(older_than_days: i64) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+    let cutoff = retention_cutoff(now, older_than_days)?;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut deleted_count = 0;
+    
+    for link in links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash.clone(), GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<LoginActivity>().ok().flatten() {
+                if activity.created_at < cutoff {
+                    delete_entry(hash)?;
+                    // Remove the link too so link-based counts stay accurate
+                    delete_link(link.create_link_hash, GetOptions::default())?;
+                    deleted_count += 1;
+                }
+            }
+        }
+    }
+    
+    debug!("🧹 [CLEANUP] Deleted {} old login activities", deleted_count);
+    Ok(deleted_count)
+}
+
+// ============================================================================
+// DASHBOARD ACTIVITY - NEW IN v1.6
+// ============================================================================
+
+/// Store dashboard activity
+/// Returns None without storing when track_dashboard_activity is off
+#[hdk_extern]
+pub fn store_dashboard_activity(input: StoreDashboardActivityInput) -> ExternResult<Option<ActionHash>> {
+    let StoreDashboardActivityInput { activity, idempotency_key } = input;
+    if !current_privacy_settings()?.track_dashboard_activity {
+        debug!("🔐 [PRIVACY] Dashboard activity tracking is off, not storing");
+        return Ok(None);
+    }
+    
+    if let Some(existing_hash) = find_idempotent_store(LinkTypes::AgentToDashboardActivity, idempotency_key.as_deref())? {
+        debug!("🧹 [ACTIVITY] Dashboard visit already stored for this idempotency key");
+        return Ok(Some(existing_hash));
+    }
+    
+    create_dashboard_activity(activity, idempotency_key.as_deref()).map(Some)
+}
+
+/// Create a dashboard activity entry with the next seq and its link
+/// Used directly by imports, which are written whatever the tracking toggle says
+fn create_dashboard_activity(mut activity: DashboardActivity, idempotency_key: Option<&str>) -> ExternResult<ActionHash> {
+    activity.seq = Some(next_activity_seq(LinkTypes::AgentToDashboardActivity)?);
+    let activity_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::DashboardActivity(activity)
+    ))?;
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key,
+        activity_hash.clone(),
+        LinkTypes::AgentToDashboardActivity,
+        tag_with_idempotency_key(Vec::new(), idempotency_key),
+    )?;
+    
+    Ok(activity_hash)
+}
+
+/// Get dashboard activity history (paginated, newest first)
+#[hdk_extern]
+pub fn get_dashboard_activity(input: GetActivityInput) -> ExternResult<Vec<DashboardActivity>> {
+    let limit = resolve_page_size(input.limit)?;
+    let offset = resolve_offset(input.offset)?;
+    
+    load_dashboard_activity(limit, offset)
+}
+
+/// Dashboard activity items offset..offset+limit (newest first), without the MAX_PAGE_SIZE clamp
+fn load_dashboard_activity(limit: usize, offset: usize) -> ExternResult<Vec<DashboardActivity>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToDashboardActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    
+    let mut activities = Vec::new();
+    
+    for link in links.iter().rev().skip(offset).take(limit) {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash, GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<DashboardActivity>().ok().flatten() {
+                activities.push(activity);
+            }
+        }
+    }
+    
+    Ok(activities)
+}
+
+/// Delete old dashboard activity (entries and their links)
+#[hdk_extern]
+pub fn delete_old_dashboard_activity(older_than_days: i64) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+    let cutoff = retention_cutoff(now, older_than_days)?;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToDashboardActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut deleted_count = 0;
+    
+    for link in links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash.clone(), GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<DashboardActivity>().ok().flatten() {
+                if activity.created_at < cutoff {
+                    delete_entry(hash)?;
+                    // Remove the link too so link-based counts stay accurate
+                    delete_link(link.create_link_hash, GetOptions::default())?;
+                    deleted_count += 1;
+                }
+            }
+        }
+    }
+    
+    debug!("🧹 [CLEANUP] Deleted {} old dashboard activities", deleted_count);
+    Ok(deleted_count)
+}
+
+// ============================================================================
+// OAUTH ACTIVITY - NEW IN v1.6
+// ============================================================================
+
+/// Store OAuth activity
+/// The app_id is stored as the link tag so per-app queries can filter before any get
+/// Returns None without storing when track_oauth_activity is off
+#[hdk_extern]
+pub fn store_oauth_activity(input: StoreOAuthActivityInput) -> ExternResult<Option<ActionHash>> {
+    let StoreOAuthActivityInput { activity, idempotency_key } = input;
+    if !current_privacy_settings()?.track_oauth_activity {
+        debug!("🔐 [PRIVACY] OAuth activity tracking is off, not storing");
+        return Ok(None);
+    }
+    
+    if let Some(existing_hash) = find_idempotent_store(LinkTypes::AgentToOAuthActivity, idempotency_key.as_deref())? {
+        debug!("🧹 [ACTIVITY] OAuth event already stored for this idempotency key");
+        return Ok(Some(existing_hash));
+    }
+    
+    create_oauth_activity(activity, idempotency_key.as_deref()).map(Some)
+}
+
+/// Create an OAuth activity entry with the next seq and its app_id-tagged link
+/// Used directly by imports, which are written whatever the tracking toggle says
+fn create_oauth_activity(mut activity: OAuthActivity, idempotency_key: Option<&str>) -> ExternResult<ActionHash> {
+    activity.seq = Some(next_activity_seq(LinkTypes::AgentToOAuthActivity)?);
+    let app_id_tag = oauth_activity_tag(&activity, idempotency_key);
+    
+    let activity_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::OAuthActivity(activity)
+    ))?;
+    
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key,
+        activity_hash.clone(),
+        LinkTypes::AgentToOAuthActivity,
+        app_id_tag,
+    )?;
+    
+    Ok(activity_hash)
+}
+
+/// Link tag for an OAuth activity: its app_id, plus the idempotency key if any
+fn oauth_activity_tag(activity: &OAuthActivity, idempotency_key: Option<&str>) -> LinkTag {
+    tag_with_idempotency_key(activity.app_id.as_bytes().to_vec(), idempotency_key)
+}
+
+/// Get OAuth activity history (paginated, newest first)
+#[hdk_extern]
+pub fn get_oauth_activity(input: GetActivityInput) -> ExternResult<Vec<OAuthActivity>> {
+    let limit = resolve_page_size(input.limit)?;
+    let offset = resolve_offset(input.offset)?;
+    
+    load_oauth_activity(limit, offset)
+}
+
+/// OAuth activity items offset..offset+limit (newest first), without the MAX_PAGE_SIZE clamp
+fn load_oauth_activity(limit: usize, offset: usize) -> ExternResult<Vec<OAuthActivity>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    
+    let mut activities = Vec::new();
+    
+    for link in links.iter().rev().skip(offset).take(limit) {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash, GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<OAuthActivity>().ok().flatten() {
+                activities.push(activity);
+            }
+        }
+    }
+    
+    Ok(activities)
+}
+
+/// Input for app-specific OAuth activity query
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetOAuthActivityByAppInput {
+    pub app_id: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Get OAuth activity for a specific app
+/// Filters on the app_id link tag first, so only this app's records are fetched
+/// Untagged links (stored before tagging was added) are fetched and filtered by entry
+#[hdk_extern]
+pub fn get_oauth_activity_by_app(input: GetOAuthActivityByAppInput) -> ExternResult<Vec<OAuthActivity>> {
+    let limit = resolve_page_size(input.limit)?;
+    let offset = resolve_offset(input.offset)?;
+    
+    load_oauth_activity_by_app(&input.app_id, limit, offset)
+}
+
+/// One app's OAuth activity items offset..offset+limit (newest first), without the MAX_PAGE_SIZE clamp
+fn load_oauth_activity_by_app(app_id: &str, limit: usize, offset: usize) -> ExternResult<Vec<OAuthActivity>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    page_of_app_activity(&links, app_id, limit, offset, |link| {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        Ok(get(hash, GetOptions::default())?
+            .and_then(|record| record.entry().to_app_option::<OAuthActivity>().ok().flatten()))
+    })
+}
+
+/// One app's activity offset..offset+limit from `links` (oldest first), newest first
+/// `fetch` loads a link's activity; it is never called for links tagged with another app_id,
+/// and not at all once the page is full
+fn page_of_app_activity(
+    links: &[Link],
+    app_id: &str,
+    limit: usize,
+    offset: usize,
+    mut fetch: impl FnMut(&Link) -> ExternResult<Option<OAuthActivity>>,
+) -> ExternResult<Vec<OAuthActivity>> {
+    let app_id_bytes = app_id.as_bytes();
+    
+    let mut activities = Vec::new();
+    let mut skipped = 0;
+    
+    for link in links.iter().rev() {
+        if activities.len() >= limit {
+            break;
+        }
+        
+        // Tagged link for a different app - no need to fetch the record
+        let is_legacy_link = link.tag.0.is_empty();
+        if !is_legacy_link && tag_payload(&link.tag) != app_id_bytes {
+            continue;
+        }
+        
+        if let Some(activity) = fetch(link)? {
+            if activity.app_id == app_id {
+                // Apply pagination as matches are found
+                if skipped < offset {
+                    skipped += 1;
+                } else {
+                    activities.push(activity);
+                }
+            }
+        }
+    }
+    
+    Ok(activities)
+}
+
+/// Scopes from the app's most recent consent event
+/// Empty if the app was never granted consent or the latest consent event is a revocation
+#[hdk_extern]
+pub fn get_app_scopes(app_id: String) -> ExternResult<Vec<String>> {
+    let activities = load_oauth_activity_by_app(&app_id, usize::MAX, 0)?;
+    
+    Ok(latest_consent_scopes(activities))
+}
+
+/// Scopes of the newest consent_granted/revoked event, empty when that event is a revocation
+fn latest_consent_scopes(activities: Vec<OAuthActivity>) -> Vec<String> {
+    activities
+        .into_iter()
+        .filter(|activity| activity.event_type == "consent_granted" || activity.event_type == "revoked")
+        .max_by_key(|activity| activity.timestamp)
+        .filter(|activity| activity.event_type == "consent_granted")
+        .map(|activity| activity.scopes)
+        .unwrap_or_default()
+}
+
+/// Distinct app_ids with any OAuth event in the range (e.g. "apps you used last month"), sorted
+#[hdk_extern]
+pub fn get_active_apps_between(input: DayRangeInput) -> ExternResult<Vec<String>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut activities = Vec::new();
+    
+    for link in links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash, GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<OAuthActivity>().ok().flatten() {
+                activities.push(activity);
+            }
+        }
+    }
+    
+    Ok(apps_active_between(activities, input.start, input.end))
+}
+
+/// Sorted distinct app_ids of the activities in [start, end)
+fn apps_active_between(activities: Vec<OAuthActivity>, start: i64, end: i64) -> Vec<String> {
+    let app_ids: std::collections::BTreeSet<String> = activities
+        .into_iter()
+        .filter(|activity| activity.timestamp >= start && activity.timestamp < end)
+        .map(|activity| activity.app_id)
+        .collect();
+    app_ids.into_iter().collect()
+}
+
+/// Delete old OAuth activity (entries and their links)
+#[hdk_extern]
+pub fn delete_old_oauth_activity(older_than_days: i64) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+    let cutoff = retention_cutoff(now, older_than_days)?;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut deleted_count = 0;
+    
+    for link in links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash.clone(), GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<OAuthActivity>().ok().flatten() {
+                if activity.created_at < cutoff {
+                    delete_entry(hash)?;
+                    // Remove the link too so link-based counts stay accurate
+                    delete_link(link.create_link_hash, GetOptions::default())?;
+                    deleted_count += 1;
+                }
+            }
+        }
+    }
+    
+    debug!("🧹 [CLEANUP] Deleted {} old OAuth activities", deleted_count);
+    Ok(deleted_count)
+}
+
+/// Delete all OAuth activity for a specific app (e.g., when the user removes the app)
+/// Deletes entries and their links; tagged links for other apps are skipped without a fetch
+#[hdk_extern]
+pub fn delete_oauth_activity_for_app(app_id: String) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let deletions = app_activity_deletions(&links, &app_id, |link| {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        Ok(get(hash, GetOptions::default())?
+            .and_then(|record| record.entry().to_app_option::<OAuthActivity>().ok().flatten()))
+    })?;
+    
+    let mut deleted_count = 0;
+    for (hash, link_hash) in deletions {
+        delete_entry(hash)?;
+        delete_link(link_hash, GetOptions::default())?;
+        deleted_count += 1;
+    }
+    
+    debug!("🧹 [CLEANUP] Deleted {} OAuth activities for app {}", deleted_count, app_id);
+    Ok(deleted_count)
+}
+
+/// (activity hash, link hash) of every activity for `app_id`
+/// `fetch` loads a link's activity; it is never called for links tagged with another app_id
+fn app_activity_deletions(
+    links: &[Link],
+    app_id: &str,
+    mut fetch: impl FnMut(&Link) -> ExternResult<Option<OAuthActivity>>,
+) -> ExternResult<Vec<(ActionHash, ActionHash)>> {
+    let mut deletions = Vec::new();
+    
+    for link in links {
+        let is_legacy_link = link.tag.0.is_empty();
+        if !is_legacy_link && tag_payload(&link.tag) != app_id.as_bytes() {
+            continue;
+        }
+        
+        if fetch(link)?.is_some_and(|activity| activity.app_id == app_id) {
+            let hash = ActionHash::try_from(link.target.clone())
+                .map_err(|_| wasm_error!("Invalid activity hash"))?;
+            deletions.push((hash, link.create_link_hash.clone()));
+        }
+    }
+    
+    Ok(deletions)
+}
+
+// ============================================================================
+// ACTIVITY SUMMARY - CONVENIENCE FUNCTION
+// ============================================================================
+
+/// Activity summary (for dashboard display)
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ActivitySummary {
+    pub total_logins: u32,
+    pub logins_last_30_days: u32,
+    pub unique_apps_used: u32,
+    pub dashboard_visits: u32,
+    pub last_login: Option<i64>,
+}
+
+/// Aggregate per-login timestamps, the distinct OAuth app count and the dashboard link count
+/// Shared by the record and link-tag paths so both summarize the same inputs the same way
+fn summarize_activity(
+    login_timestamps: &[i64],
+    unique_apps_used: usize,
+    dashboard_visits: u32,
+    thirty_days_ago: i64,
+) -> ActivitySummary {
+    ActivitySummary {
+        total_logins: login_timestamps.len() as u32,
+        logins_last_30_days: login_timestamps.iter().filter(|timestamp| **timestamp >= thirty_days_ago).count() as u32,
+        unique_apps_used: unique_apps_used as u32,
+        dashboard_visits,
+        last_login: login_timestamps.iter().max().copied(),
+    }
+}
+
+/// Get activity summary (counts and stats)
+/// Counts one login / app per activity link whose record can be fetched; records deleted while
+/// their link remains are still fetched (and counted), exactly as get_activity_summary_fast counts them
+#[hdk_extern]
+pub fn get_activity_summary(_: ()) -> ExternResult<ActivitySummary> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+    let thirty_days_ago = now - (30 * 24 * 60 * 60 * 1_000_000);
+    
+    // Count total logins
+    let login_links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut login_activities = Vec::new();
+    
+    for link in login_links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid hash"))?;
+        
+        if let Some(record) = get(hash, GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<LoginActivity>().ok().flatten() {
+                login_activities.push(activity);
+            }
+        }
+    }
+    
+    // Count dashboard visits
+    let dashboard_links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToDashboardActivity)?,
+        GetStrategy::default()
+    )?;
+    let dashboard_visits = dashboard_links.len() as u32;
+    
+    // Count unique OAuth apps
+    let oauth_links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut oauth_activities = Vec::new();
+    for link in oauth_links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid hash"))?;
+        
+        if let Some(record) = get(hash, GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<OAuthActivity>().ok().flatten() {
+                oauth_activities.push(activity);
+            }
+        }
+    }
+    
+    Ok(summary_from_records(&login_activities, &oauth_activities, dashboard_visits, thirty_days_ago))
+}
+
+/// Summary from fetched login and OAuth records (the get_activity_summary path)
+fn summary_from_records(
+    login_activities: &[LoginActivity],
+    oauth_activities: &[OAuthActivity],
+    dashboard_visits: u32,
+    thirty_days_ago: i64,
+) -> ActivitySummary {
+    let login_timestamps: Vec<i64> = login_activities.iter().map(|activity| activity.timestamp).collect();
+    let app_ids: std::collections::HashSet<&str> = oauth_activities.iter().map(|activity| activity.app_id.as_str()).collect();
+    summarize_activity(&login_timestamps, app_ids.len(), dashboard_visits, thirty_days_ago)
+}
+
+/// Summary from login and OAuth link tags alone (the get_activity_summary_fast path)
+/// None if any link predates tagging, so the caller has to fall back to the records
+fn summary_from_links(
+    login_links: &[Link],
+    oauth_links: &[Link],
+    dashboard_visits: u32,
+    thirty_days_ago: i64,
+) -> Option<ActivitySummary> {
+    let login_timestamps = login_links
+        .iter()
+        .map(|link| tag_timestamp(&link.tag))
+        .collect::<Option<Vec<i64>>>()?;
+    if oauth_links.iter().any(|link| link.tag.0.is_empty()) {
+        return None;
+    }
+    let app_ids: std::collections::HashSet<&[u8]> = oauth_links
+        .iter()
+        .map(|link| tag_payload(&link.tag))
+        .collect();
+    Some(summarize_activity(&login_timestamps, app_ids.len(), dashboard_visits, thirty_days_ago))
+}
+
+/// get_activity_summary computed from link tags and counts without any get
+/// Both count the same links; they only differ while a linked record can't be fetched yet
+/// (e.g., not gossiped to this node), which the slow path skips and this path still counts
+/// Falls back to get_activity_summary if any login or OAuth link predates tagging
+#[hdk_extern]
+pub fn get_activity_summary_fast(_: ()) -> ExternResult<ActivitySummary> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let now = sys_time()?.as_micros();
+    let thirty_days_ago = now - (30 * 24 * 60 * 60 * 1_000_000);
+    
+    let login_links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    let oauth_links = get_links(
+        LinkQuery::try_new(my_agent_pub_key.clone(), LinkTypes::AgentToOAuthActivity)?,
+        GetStrategy::default()
+    )?;
+    let dashboard_visits = count_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToDashboardActivity)?
+    )? as u32;
+    
+    match summary_from_links(&login_links, &oauth_links, dashboard_visits, thirty_days_ago) {
+        Some(summary) => Ok(summary),
+        None => {
+            debug!("📊 [SUMMARY] Untagged activity links found, using slow path");
+            get_activity_summary(())
+        }
+    }
+}
+
+/// Input for time-range activity queries (microsecond timestamps, start inclusive, end exclusive)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DayRangeInput {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Microseconds in one (UTC) day
+const MICROS_PER_DAY: i64 = 24 * 60 * 60 * 1_000_000;
+
+/// Start of the UTC day containing `micros` (microseconds since the Unix epoch)
+/// Timezone-agnostic: days are fixed 86_400-second spans from the epoch, so pre-1970 timestamps
+/// floor downward too. Heatmap buckets and retention cutoffs both go through this so they agree
+pub fn floor_to_day(micros: i64) -> i64 {
+    micros - micros.rem_euclid(MICROS_PER_DAY)
+}
+
+/// Cutoff for delete_old_*: the start of today (UTC) minus `older_than_days` whole days
+/// Aligned to day boundaries so deletion removes exactly the heatmap buckets older than the cutoff
+/// Negative days would put the cutoff in the future and delete everything, so they are rejected
+fn retention_cutoff(now: i64, older_than_days: i64) -> ExternResult<i64> {
+    if older_than_days < 0 {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "older_than_days cannot be negative (got {})", older_than_days
+        ))));
+    }
+    older_than_days
+        .checked_mul(MICROS_PER_DAY)
+        .and_then(|span| floor_to_day(now).checked_sub(span))
+        .ok_or(wasm_error!("older_than_days is too large"))
+}
+
+/// Login counts per day for a heatmap view
+/// Returns (day-start-micros, count) for each day in the range with at least one login, oldest first
+#[hdk_extern]
+pub fn get_login_counts_by_day(input: DayRangeInput) -> ExternResult<Vec<(i64, u32)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToLoginActivity)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut timestamps = Vec::new();
+    
+    for link in links {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        
+        if let Some(record) = get(hash, GetOptions::default())? {
+            if let Some(activity) = record.entry().to_app_option::<LoginActivity>().ok().flatten() {
+                timestamps.push(activity.timestamp);
+            }
+        }
+    }
+    
+    Ok(count_by_day(timestamps, input.start, input.end))
+}
+
+/// (day-start-micros, count) for timestamps in [start, end), oldest day first
+fn count_by_day(timestamps: Vec<i64>, start: i64, end: i64) -> Vec<(i64, u32)> {
+    let mut buckets: std::collections::BTreeMap<i64, u32> = std::collections::BTreeMap::new();
+    for timestamp in timestamps.into_iter().filter(|timestamp| *timestamp >= start && *timestamp < end) {
+        *buckets.entry(floor_to_day(timestamp)).or_insert(0) += 1;
+    }
+    buckets.into_iter().collect()
+}
+
+/// Which activity types have any data (lets the UI hide empty tabs)
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ActivityAvailability {
+    pub login: bool,
+    pub dashboard: bool,
+    pub oauth: bool,
+}
+
+/// Report which activity types have data, from link counts alone (no record fetches)
+#[hdk_extern]
+pub fn get_activity_availability(_: ()) -> ExternResult<ActivityAvailability> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    activity_availability(|link_type| count_links(LinkQuery::try_new(my_agent_pub_key.clone(), link_type)?))
+}
+
+/// Availability flags from `count`, which returns how many links of a type exist
+fn activity_availability(mut count: impl FnMut(LinkTypes) -> ExternResult<usize>) -> ExternResult<ActivityAvailability> {
+    Ok(ActivityAvailability {
+        login: count(LinkTypes::AgentToLoginActivity)? > 0,
+        dashboard: count(LinkTypes::AgentToDashboardActivity)? > 0,
+        oauth: count(LinkTypes::AgentToOAuthActivity)? > 0,
+    })
+}
+
+/// Earliest and latest timestamp of one activity type (both None when there is no activity)
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TimestampBounds {
+    pub first: Option<i64>,
+    pub last: Option<i64>,
+}
+
+impl TimestampBounds {
+    /// Widen the bounds to cover `timestamp`
+    fn include(&mut self, timestamp: i64) {
+        self.first = Some(self.first.map_or(timestamp, |first| first.min(timestamp)));
+        self.last = Some(self.last.map_or(timestamp, |last| last.max(timestamp)));
+    }
+}
+
+/// Date-range picker bounds for every activity type
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActivityBounds {
+    pub login: TimestampBounds,
+    pub dashboard: TimestampBounds,
+    pub oauth: TimestampBounds,
+}
+
+/// First/last timestamp per activity type, in a single pass over each type's links
+/// Tagged login links are read from the tag; everything else is fetched
+#[hdk_extern]
+pub fn get_activity_bounds(_: ()) -> ExternResult<ActivityBounds> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    let links_of = |link_type: LinkTypes| -> ExternResult<Vec<Link>> {
+        get_links(LinkQuery::try_new(my_agent_pub_key.clone(), link_type)?, GetStrategy::default())
+    };
+    let fetch = |link: &Link| -> ExternResult<Option<Record>> {
+        let hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid activity hash"))?;
+        get(hash, GetOptions::default())
+    };
+    
+    let login = login_bounds(links_of(LinkTypes::AgentToLoginActivity)?, |link| {
+        Ok(fetch(link)?.and_then(|record| record.entry().to_app_option::<LoginActivity>().ok().flatten()))
+    })?;
+    
+    let mut dashboard = TimestampBounds::default();
+    for link in links_of(LinkTypes::AgentToDashboardActivity)? {
+        if let Some(activity) = fetch(&link)?.and_then(|record| record.entry().to_app_option::<DashboardActivity>().ok().flatten()) {
+            dashboard.include(activity.visit_timestamp);
+        }
+    }
+    
+    let mut oauth = TimestampBounds::default();
+    for link in links_of(LinkTypes::AgentToOAuthActivity)? {
+        if let Some(activity) = fetch(&link)?.and_then(|record| record.entry().to_app_option::<OAuthActivity>().ok().flatten()) {
+            oauth.include(activity.timestamp);
+        }
+    }
+    
+    Ok(ActivityBounds { login, dashboard, oauth })
+}
+
+/// Login bounds from the link tags, calling `fetch` only for untagged (pre-tagging) links
+fn login_bounds(
+    links: Vec<Link>,
+    mut fetch: impl FnMut(&Link) -> ExternResult<Option<LoginActivity>>,
+) -> ExternResult<TimestampBounds> {
+    let mut bounds = TimestampBounds::default();
+    for link in links {
+        let timestamp = match tag_timestamp(&link.tag) {
+            Some(timestamp) => Some(timestamp),
+            None => fetch(&link)?.map(|activity| activity.timestamp),
+        };
+        if let Some(timestamp) = timestamp {
+            bounds.include(timestamp);
+        }
+    }
+    Ok(bounds)
+}
+
+// ============================================================================
+// ZERO-KNOWLEDGE ANALYTICS (v1.9)
+// ============================================================================
+
+/// Store a random analytics_id for an app (first time user logs in to that app)
+/// This ID is mathematically impossible to link to the user's DID without their password
+#[hdk_extern]
+pub fn store_analytics_id(input: AppAnalyticsId) -> ExternResult<Record> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Check if analytics_id already exists for this app
+    let existing = get_analytics_id_for_app(input.app_id.clone())?;
+    if existing.is_some() {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest("Analytics ID already exists for this app".into())
+        ));
+    }
+    
+    // Create the entry
+    let analytics_id_hash = create_entry(EntryTypes::AppAnalyticsId(input.clone()))?;
+    
+    // Link from agent to analytics_id, using app_id as the tag for fast lookup
+    create_link(
+        my_agent_pub_key.clone(),
+        analytics_id_hash.clone(),
+        LinkTypes::AgentToAppAnalyticsId,
+        LinkTag::from(input.app_id.as_bytes().to_vec()),
+    )?;
+    
+    // Get and return the created record
+    let record = get(analytics_id_hash, GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Could not find the newly created analytics_id".into())
+        ))?;
+    
+    Ok(record)
+}
+
+/// Get analytics_id for a specific app (returns None if user hasn't logged in to that app yet)
+/// Uses RECURSIVE loop to follow update chain (in case analytics_id ever needs updating)
+#[hdk_extern]
+pub fn get_analytics_id_for_app(app_id: String) -> ExternResult<Option<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Get all links for this agent
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToAppAnalyticsId)?,
+        GetStrategy::default()
+    )?;
+    
+    // Find link with matching app_id tag
+    for link in links {
+        let tag_bytes = link.tag.0;
+        let tag_string = String::from_utf8(tag_bytes).unwrap_or_default();
+        
+        if tag_string == app_id {
+            let analytics_hash = ActionHash::try_from(link.target.clone())
+                .map_err(|_| wasm_error!(
+                    WasmErrorInner::Guest("Invalid analytics_id hash".into())
+                ))?;
+            
+            // ✅ CRITICAL: Follow the ENTIRE update chain
+            let record = latest_in_chain(analytics_hash)?
+                .ok_or(wasm_error!(
+                    WasmErrorInner::Guest("Analytics ID not found in chain".into())
+                ))?;
+            return Ok(Some(record));
+        }
+    }
+    
+    // No analytics_id found for this app
+    Ok(None)
+}
+
+/// Get all analytics_ids for this user (used for support consent)
+/// Returns a Vec of AppAnalyticsId entries
+#[hdk_extern]
+pub fn get_all_analytics_ids(_: ()) -> ExternResult<Vec<AppAnalyticsId>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Get all analytics_id links
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToAppAnalyticsId)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut analytics_ids = Vec::new();
+    
+    for link in links {
+        let analytics_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!(
+                WasmErrorInner::Guest("Invalid analytics_id hash".into())
+            ))?;
+        
+        // ✅ CRITICAL: Follow the ENTIRE update chain
+        let record = latest_in_chain(analytics_hash)?
+            .ok_or(wasm_error!(
+                WasmErrorInner::Guest("Analytics ID not found".into())
+            ))?;
+        
+        // Decode the entry
+        if let Some(Entry::App(app_entry_bytes)) = record.entry().as_option() {
+            let analytics_id = AppAnalyticsId::try_from(
+                SerializedBytes::from(UnsafeBytes::from(app_entry_bytes.bytes().to_vec()))
+            ).map_err(|e| wasm_error!(
+                WasmErrorInner::Guest(format!("Failed to deserialize: {:?}", e))
+            ))?;
+            analytics_ids.push(analytics_id);
+        }
+    }
+    
+    Ok(analytics_ids)
+}
+
+/// Generate a fresh random analytics_id (UUID v4 format)
+fn generate_analytics_id() -> ExternResult<String> {
+    let bytes: [u8; 16] = random_bytes(16)?
+        .into_vec()
+        .try_into()
+        .map_err(|_| wasm_error!("random_bytes returned the wrong length"))?;
+    Ok(format_uuid_v4(bytes))
+}
+
+/// Format 16 random bytes as a UUID v4 string, setting the version and variant bits
+fn format_uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;  // Version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;  // RFC 4122 variant
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]
+    )
+}
+
+/// The entry that replaces `current` on rotation: same app, new ID, stamped with `now`
+fn rotated_analytics_id(current: &AppAnalyticsId, analytics_id: String, now: i64) -> AppAnalyticsId {
+    AppAnalyticsId {
+        app_id: current.app_id.clone(),
+        analytics_id,
+        created_at: now,
+    }
+}
+
+/// Rotate the analytics_id for an app (e.g., if user suspects it was correlated)
+/// Creates an update on the existing chain so get_analytics_id_for_app returns the new ID
+#[hdk_extern]
+pub fn rotate_analytics_id(app_id: String) -> ExternResult<AppAnalyticsId> {
+    let current_record = get_analytics_id_for_app(app_id)?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("No analytics ID found for this app".into())
+        ))?;
+    let current: AppAnalyticsId = current_record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!("Malformed analytics ID"))?;
+
+    let rotated = rotated_analytics_id(&current, generate_analytics_id()?, sys_time()?.as_micros());
+
+    update_entry(
+        current_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::AppAnalyticsId(rotated.clone())),
+    )?;
+
+    debug!("📊 [ANALYTICS] Rotated analytics_id for app: {}", rotated.app_id);
+    Ok(rotated)
+}
+
+/// Remove duplicate analytics IDs so each app maps to exactly one (e.g., after a racing store or a re-import)
+/// Per app_id keeps the chain created first (by the original entry's created_at, so rotations don't
+/// count as newer) and deletes the others with their links. Returns how many chains were removed
+#[hdk_extern]
+pub fn dedupe_analytics_ids(_: ()) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToAppAnalyticsId)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut linked = Vec::new();
+    for link in links {
+        let analytics_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid analytics_id hash"))?;
+        
+        if let Some(record) = get(analytics_hash.clone(), GetOptions::default())? {
+            if let Some(analytics_id) = record.entry().to_app_option::<AppAnalyticsId>().ok().flatten() {
+                linked.push((analytics_id, analytics_hash, link.create_link_hash));
+            }
+        }
+    }
+    
+    let duplicates = duplicate_analytics_ids(linked);
+    for (analytics_hash, link_hash) in &duplicates {
+        if let Some(analytics_hash) = analytics_hash {
+            delete_update_tree(analytics_hash.clone())?;
+        }
+        delete_link(link_hash.clone(), GetOptions::default())?;
+    }
+    
+    debug!("📊 [ANALYTICS] Removed {} duplicate analytics_ids", duplicates.len());
+    Ok(duplicates.len() as u32)
+}
+
+/// The links (and entries) to remove so each app_id keeps only its oldest analytics ID
+/// Takes (analytics ID, its action hash, its link's create hash) per link. Each result pairs the entry
+/// to delete - None for a second link to the kept entry, which only needs the link removed - with its link
+fn duplicate_analytics_ids(linked: Vec<(AppAnalyticsId, ActionHash, ActionHash)>) -> Vec<(Option<ActionHash>, ActionHash)> {
+    let mut by_app: std::collections::BTreeMap<String, Vec<(i64, ActionHash, ActionHash)>> = std::collections::BTreeMap::new();
+    for (analytics_id, analytics_hash, link_hash) in linked {
+        by_app
+            .entry(analytics_id.app_id)
+            .or_default()
+            .push((analytics_id.created_at, analytics_hash, link_hash));
+    }
+    
+    let mut duplicates = Vec::new();
+    for mut chains in by_app.into_values() {
+        // Oldest first (ties go to the lower action hash) - keep index 0, delete the rest
+        chains.sort_by(|(a_created, a_hash, _), (b_created, b_hash, _)| {
+            a_created.cmp(b_created).then_with(|| a_hash.cmp(b_hash))
+        });
+        let Some(kept_hash) = chains.first().map(|(_, hash, _)| hash.clone()) else {
+            continue;
+        };
+        for (_, analytics_hash, link_hash) in chains.into_iter().skip(1) {
+            let entry = (analytics_hash != kept_hash).then_some(analytics_hash);
+            duplicates.push((entry, link_hash));
+        }
+    }
+    
+    duplicates
+}
+
+// ============================================================================
+// TWO-FACTOR AUTHENTICATION (v1.10)
+// ============================================================================
+
+/// Store TOTP configuration on private DHT
+/// Called after user verifies their first TOTP code during setup
+#[hdk_extern]
+pub fn store_totp_config(config: TotpConfig) -> ExternResult<Record> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    // Ensure no existing TOTP config (must disable first to re-enable)
+    let existing = get_totp_config(())?;
+    if existing.is_some() {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest("TOTP config already exists. Disable 2FA first.".into())
+        ));
+    }
+    
+    let config_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::TotpConfig(config.clone())
+    ))?;
+    
+    create_link(
+        my_agent_pub_key,
+        config_hash.clone(),
+        LinkTypes::AgentToTotpConfig,
+        (),
+    )?;
+    
+    let record = get(config_hash, GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Could not find newly created TOTP config".into())
+        ))?;
+    
+    Ok(record)
+}
+
+/// Get the current agent's TOTP configuration
+/// Uses RECURSIVE loop to follow entire update chain (critical pattern from v1.5+)
+#[hdk_extern]
+pub fn get_totp_config(_: ()) -> ExternResult<Option<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToTotpConfig)?,
+        GetStrategy::default()
+    )?;
+    
+    if let Some(link) = links.first() {
+        let totp_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!(
+                WasmErrorInner::Guest("Invalid TOTP config hash".into())
+            ))?;
+        
+        // ✅ CRITICAL: Follow ENTIRE update chain
+        // When update_totp_config is called multiple times (e.g., during repeated
+        // password changes), each creates a new update.
+        let record = latest_in_chain(totp_hash)?
+            .ok_or(wasm_error!(
+                WasmErrorInner::Guest("TOTP config not found in chain".into())
+            ))?;
+        return Ok(Some(record));
+    }
+    
+    Ok(None)
+}
+
+/// Update TOTP configuration (e.g., re-encrypt after password change, update backup codes)
+#[hdk_extern]
+pub fn update_totp_config(config: TotpConfig) -> ExternResult<Record> {
+    let current_record = get_totp_config(())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("No TOTP config found to update".into())
+        ))?;
+    
+    let updated_hash = update_entry(
+        current_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::TotpConfig(config)),
+    )?;
+    
+    let record = get(updated_hash, GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Could not find the updated TOTP config".into())
+        ))?;
+    
+    Ok(record)
+}
+
+/// Disable 2FA by setting enabled = false and clearing encrypted data
+#[hdk_extern]
+pub fn disable_totp(_: ()) -> ExternResult<Record> {
+    let current_record = get_totp_config(())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("No TOTP config found to disable".into())
+        ))?;
+    
+    let mut config: TotpConfig = current_record
+        .entry()
+        .to_app_option()
+        .map_err(|e| wasm_error!(e))?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Malformed TOTP config".into())
+        ))?;
+    
+    config.enabled = false;
+    config.updated_at = sys_time()?.as_micros();
+    
+    let updated_hash = update_entry(
+        current_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::TotpConfig(config)),
+    )?;
+    
+    let record = get(updated_hash, GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Could not find the disabled TOTP config".into())
+        ))?;
+
+    Ok(record)
+}
+
+// ============================================================================
+// PROFILE PICTURE - NEW IN v1.11
+// Moved from public identity DNA to private DNA for pseudonymity
+// ============================================================================
+
+/// Store profile picture on private DHT
+#[hdk_extern]
+pub fn store_profile_picture(picture: ProfilePicture) -> ExternResult<Record> {
+    // Ensure no existing profile picture (use update instead)
+    if get_profile_picture(())?.is_some() {
+        return Err(wasm_error!(
+            WasmErrorInner::Guest("Profile picture already exists. Use update_profile_picture instead.".into())
+        ));
+    }
+
+    let picture_hash = create_entry(&EntryZomes::IntegrityPrivateData(
+        EntryTypes::ProfilePicture(picture.clone())
+    ))?;
+
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    create_link(
+        my_agent_pub_key,
+        picture_hash.clone(),
+        LinkTypes::AgentToProfilePicture,
+        (),
+    )?;
+
+    let record = get(picture_hash, GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Could not find newly created profile picture".into())
+        ))?;
+
+    Ok(record)
+}
+
+/// Get the current agent's profile picture
+#[hdk_extern]
+pub fn get_profile_picture(_: ()) -> ExternResult<Option<Record>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, LinkTypes::AgentToProfilePicture)?,
+        GetStrategy::default()
+    )?;
+
+    if let Some(link) = links.first() {
+        let picture_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!(
+                WasmErrorInner::Guest("Invalid profile picture hash".into())
+            ))?;
+
+        // Follow update chain recursively
+        let record = latest_in_chain(picture_hash)?
+            .ok_or(wasm_error!(
+                WasmErrorInner::Guest("Profile picture not found in chain".into())
+            ))?;
+        return Ok(Some(record));
+    }
+
+    Ok(None)
+}
+
+/// Update the current agent's profile picture
+#[hdk_extern]
+pub fn update_profile_picture(picture: ProfilePicture) -> ExternResult<Record> {
+    let current_record = get_profile_picture(())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("No profile picture found to update".into())
+        ))?;
+
+    let updated_hash = update_entry(
+        current_record.action_address().clone(),
+        &EntryZomes::IntegrityPrivateData(EntryTypes::ProfilePicture(picture)),
+    )?;
+
+    let record = get(updated_hash, GetOptions::default())?
+        .ok_or(wasm_error!(
+            WasmErrorInner::Guest("Could not find the updated profile picture".into())
+        ))?;
+
+    Ok(record)
+}
+
+
+// ============================================================================
+// ENTRY SCHEMAS - Machine-readable entry shapes for integrators
+// ============================================================================
+
+/// One field of an entry type as it appears in serialized form
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldSpec {
+    pub name: String,
+    pub json_type: String,   // "string", "integer", "boolean" or "array"
+    pub required: bool,      // false for Option fields and fields with a serde default
+    pub nullable: bool,      // true for Option fields
+}
+
+/// Fields of one entry type
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EntrySchema {
+    pub entry_type: String,
+    pub fields: Vec<FieldSpec>,
+}
+
+/// Field that must always be present
+fn required(name: &str, json_type: &str) -> FieldSpec {
+    FieldSpec { name: name.into(), json_type: json_type.into(), required: true, nullable: false }
+}
+
+/// Option<T> field - may be null or missing
+fn optional(name: &str, json_type: &str) -> FieldSpec {
+    FieldSpec { name: name.into(), json_type: json_type.into(), required: false, nullable: true }
+}
+
+/// Field with a serde default - may be missing on older entries
+fn defaulted(name: &str, json_type: &str) -> FieldSpec {
+    FieldSpec { name: name.into(), json_type: json_type.into(), required: false, nullable: false }
+}
+
+/// Every entry type, in EntryTypes order
+const ENTRY_TYPES: [UnitEntryTypes; 12] = [
+    UnitEntryTypes::UserProfile,
+    UnitEntryTypes::RecoveryPhrase,
+    UnitEntryTypes::Session,
+    UnitEntryTypes::EmailPermission,
+    UnitEntryTypes::LoginActivity,
+    UnitEntryTypes::DashboardActivity,
+    UnitEntryTypes::OAuthActivity,
+    UnitEntryTypes::PrivacySettings,
+    UnitEntryTypes::AppAnalyticsId,
+    UnitEntryTypes::TotpConfig,
+    UnitEntryTypes::ProfilePicture,
+    UnitEntryTypes::ImportCheckpoint,
+];
+
+/// Fields of each entry type, mirroring the integrity structs
+/// The match is exhaustive, so adding an entry type without a schema fails to compile
+fn entry_schema_fields(entry_type: UnitEntryTypes) -> Vec<FieldSpec> {
+    match entry_type {
+        UnitEntryTypes::UserProfile => vec![
+            required("encrypted_email", "string"),
+            required("nonce", "string"),
+            required("salt", "string"),
+            required("tag", "string"),
+            optional("username", "string"),
+            required("display_name", "string"),
+            required("created_at", "integer"),
+            required("updated_at", "integer"),
+        ],
+        UnitEntryTypes::RecoveryPhrase => vec![
+            required("encrypted_mnemonic", "string"),
+            required("nonce", "string"),
+            required("salt", "string"),
+            required("tag", "string"),
+            required("verified", "boolean"),
+            required("created_at", "integer"),
+        ],
+        UnitEntryTypes::Session => vec![
+            required("user_agent", "string"),
+            required("ip_address", "string"),
+            required("device_info", "string"),
+            required("conductor_id", "string"),
+            required("created_at", "integer"),
+            required("last_active", "integer"),
+            defaulted("is_current", "boolean"),
+        ],
+        UnitEntryTypes::EmailPermission => vec![
+            required("service_name", "string"),
+            required("purpose", "string"),
+            required("granted", "boolean"),
+            optional("granted_at", "integer"),
+            optional("revoked_at", "integer"),
+            optional("last_used_at", "integer"),
+            optional("expires_at", "integer"),
+            required("created_at", "integer"),
+            required("updated_at", "integer"),
+            defaulted("category", "string"),
+        ],
+        UnitEntryTypes::LoginActivity => vec![
+            required("timestamp", "integer"),
+            required("login_method", "string"),
+            optional("ip_address", "string"),
+            optional("user_agent", "string"),
+            required("session_id", "string"),
+            required("created_at", "integer"),
+            optional("seq", "integer"),
+            optional("region", "string"),
+        ],
+        UnitEntryTypes::DashboardActivity => vec![
+            required("visit_timestamp", "integer"),
+            required("page_path", "string"),
+            optional("duration_seconds", "integer"),
+            required("created_at", "integer"),
+            optional("seq", "integer"),
+        ],
+        UnitEntryTypes::OAuthActivity => vec![
+            required("timestamp", "integer"),
+            required("app_id", "string"),
+            required("app_name", "string"),
+            required("event_type", "string"),
+            required("created_at", "integer"),
+            optional("seq", "integer"),
+            defaulted("scopes", "array"),
+        ],
+        UnitEntryTypes::PrivacySettings => vec![
+            required("track_ip_address", "boolean"),
+            required("track_user_agent", "boolean"),
+            required("activity_log_retention_days", "integer"),
+            optional("auto_anonymize_after_days", "integer"),
+            defaulted("default_page_size", "integer"),
+            defaulted("max_sessions", "integer"),
+            defaulted("track_dashboard_activity", "boolean"),
+            defaulted("track_oauth_activity", "boolean"),
+            defaulted("track_region", "boolean"),
+            required("created_at", "integer"),
+            required("updated_at", "integer"),
+        ],
+        UnitEntryTypes::AppAnalyticsId => vec![
+            required("app_id", "string"),
+            required("analytics_id", "string"),
+            required("created_at", "integer"),
+        ],
+        UnitEntryTypes::TotpConfig => vec![
+            required("encrypted_secret", "string"),
+            required("nonce", "string"),
+            required("salt", "string"),
+            required("tag", "string"),
+            required("encrypted_backup_codes", "string"),
+            required("backup_nonce", "string"),
+            required("backup_salt", "string"),
+            required("backup_tag", "string"),
+            required("enabled", "boolean"),
+            required("created_at", "integer"),
+            required("updated_at", "integer"),
+        ],
+        UnitEntryTypes::ProfilePicture => vec![
+            required("profile_picture", "string"),
+            required("has_custom_picture", "boolean"),
+            required("updated_at", "integer"),
+        ],
+        UnitEntryTypes::ImportCheckpoint => vec![
+            required("export_timestamp", "integer"),
+            required("total", "integer"),
+            required("next_index", "integer"),
+            required("created_at", "integer"),
+            required("updated_at", "integer"),
+        ],
+    }
+}
+
+/// Field list of every entry type
+/// Lets external tooling validate bundles before calling import_data
+#[hdk_extern]
+pub fn get_entry_schemas(_: ()) -> ExternResult<Vec<EntrySchema>> {
+    Ok(entry_schemas())
+}
+
+/// Schemas for ENTRY_TYPES, in order
+fn entry_schemas() -> Vec<EntrySchema> {
+    ENTRY_TYPES
+        .into_iter()
+        .map(|entry_type| EntrySchema {
+            entry_type: format!("{:?}", entry_type),
+            fields: entry_schema_fields(entry_type),
+        })
+        .collect()
+}
+
+// ============================================================================
+// LINK MAINTENANCE - Orphaned link diagnostics
+// ============================================================================
+
+/// Per-type counts of AgentTo* links whose targets no longer resolve
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LinkAudit {
+    pub profile: u32,
+    pub recovery_phrase: u32,
+    pub sessions: u32,
+    pub email_permissions: u32,
+    pub login_activity: u32,
+    pub dashboard_activity: u32,
+    pub oauth_activity: u32,
+    pub privacy_settings: u32,
+    pub analytics_ids: u32,
+    pub totp_config: u32,
+    pub profile_picture: u32,
+    pub import_checkpoint: u32,
+    pub total: u32,
+}
+
+/// What a link's target resolves to
+#[derive(Debug, Clone, PartialEq)]
+enum LinkTarget {
+    Live,
+    Deleted,   // A Delete for the target has been seen
+    Missing,   // Can't be fetched - may simply not have reached this node yet
+}
+
+/// Classify a link target from its get_details result
+fn link_target(details: Option<Details>) -> LinkTarget {
+    match details {
+        Some(Details::Record(record_details)) if record_details.deletes.is_empty() => LinkTarget::Live,
+        Some(Details::Record(_)) => LinkTarget::Deleted,
+        Some(Details::Entry(entry_details)) => {
+            if newest_live_action(&entry_details.actions, &entry_details.deletes).is_some() {
+                LinkTarget::Live
+            } else if entry_details.deletes.is_empty() {
+                LinkTarget::Missing
+            } else {
+                LinkTarget::Deleted
+            }
+        }
+        None => LinkTarget::Missing,
+    }
+}
+
+/// Find links of the given type whose target is missing or has been deleted, with which it is
+fn find_orphaned_links(link_type: LinkTypes) -> ExternResult<Vec<(Link, LinkTarget)>> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, link_type)?,
+        GetStrategy::default()
+    )?;
+
+    let mut orphaned = Vec::new();
+    for link in links {
+        let target_hash = ActionHash::try_from(link.target.clone())
+            .map_err(|_| wasm_error!("Invalid link target hash"))?;
+
+        match link_target(get_details(target_hash, GetOptions::default())?) {
+            LinkTarget::Live => {}
+            target => orphaned.push((link, target)),
+        }
+    }
+
+    Ok(orphaned)
+}
+
+/// Run a per-link-type count over every AgentTo* link type
+fn build_link_audit(mut count: impl FnMut(LinkTypes) -> ExternResult<u32>) -> ExternResult<LinkAudit> {
+    let mut audit = LinkAudit {
+        profile: count(LinkTypes::AgentToProfile)?,
+        recovery_phrase: count(LinkTypes::AgentToRecoveryPhrase)?,
+        sessions: count(LinkTypes::AgentToSessions)?,
+        email_permissions: count(LinkTypes::AgentToEmailPermissions)?,
+        login_activity: count(LinkTypes::AgentToLoginActivity)?,
+        dashboard_activity: count(LinkTypes::AgentToDashboardActivity)?,
+        oauth_activity: count(LinkTypes::AgentToOAuthActivity)?,
+        privacy_settings: count(LinkTypes::AgentToPrivacySettings)?,
+        analytics_ids: count(LinkTypes::AgentToAppAnalyticsId)?,
+        totp_config: count(LinkTypes::AgentToTotpConfig)?,
+        profile_picture: count(LinkTypes::AgentToProfilePicture)?,
+        import_checkpoint: count(LinkTypes::AgentToImportCheckpoint)?,
+        total: 0,
+    };
+
+    audit.total = audit.profile
+        + audit.recovery_phrase
+        + audit.sessions
+        + audit.email_permissions
+        + audit.login_activity
+        + audit.dashboard_activity
+        + audit.oauth_activity
+        + audit.privacy_settings
+        + audit.analytics_ids
+        + audit.totp_config
+        + audit.profile_picture
+        + audit.import_checkpoint;
+
+    Ok(audit)
+}
+
+/// Count orphaned links (targets deleted or missing) for every AgentTo* link type
+/// Read-only health signal for ops
+#[hdk_extern]
+pub fn audit_links(_: ()) -> ExternResult<LinkAudit> {
+    let audit = build_link_audit(|link_type| {
+        Ok(find_orphaned_links(link_type)?.len() as u32)
+    })?;
+
+    debug!("🩺 [AUDIT] Found {} orphaned links", audit.total);
+    Ok(audit)
+}
+
+/// Delete links whose targets are confirmed deleted for every AgentTo* link type
+/// Self-heal counterpart to audit_links - returns the number of links removed per type
+/// Links to targets that merely can't be fetched are kept: on a partitioned or freshly joined
+/// node they are usually live data that hasn't arrived yet
+#[hdk_extern]
+pub fn repair_all_links(_: ()) -> ExternResult<LinkAudit> {
+    let audit = build_link_audit(|link_type| {
+        let mut repaired = 0;
+        for (link, target) in find_orphaned_links(link_type)? {
+            if target == LinkTarget::Deleted {
+                delete_link(link.create_link_hash, GetOptions::default())?;
+                repaired += 1;
+            }
+        }
+        Ok(repaired)
+    })?;
+
+    debug!("🧹 [REPAIR] Removed {} orphaned links", audit.total);
+    Ok(audit)
+}
+
+/// What run_maintenance changed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MaintenanceReport {
+    pub permissions_revoked: u32,
+    pub login_activities_deleted: u32,
+    pub dashboard_activities_deleted: u32,
+    pub oauth_activities_deleted: u32,
+    pub login_activities_anonymized: u32,
+    pub links_repaired: LinkAudit,
+}
+
+/// Enforce every policy in the current privacy settings in one scheduled call
+/// Revokes expired permissions, deletes activity past the retention period, strips untracked
+/// fields from login activity, then removes links left dangling by the deletions
+/// A non-positive activity_log_retention_days is treated as unset and skips retention deletion
+#[hdk_extern]
+pub fn run_maintenance(_: ()) -> ExternResult<MaintenanceReport> {
+    let settings = current_privacy_settings()?;
+    debug!("🧹 [MAINTENANCE] Running with {}-day retention", settings.activity_log_retention_days);
+    
+    let report = maintain(
+        &settings,
+        || revoke_expired_permissions(()),
+        |retention_days| Ok((
+            delete_old_login_activity(retention_days)?,
+            delete_old_dashboard_activity(retention_days)?,
+            delete_old_oauth_activity(retention_days)?,
+        )),
+        || anonymize_login_activity(()),
+        || repair_all_links(()),
+    )?;
+    
+    debug!("🧹 [MAINTENANCE] Complete: {:?}", report);
+    Ok(report)
+}
+
+/// Run each cleanup in order: revoke, retention delete (login, dashboard, OAuth counts), anonymize,
+/// then link repair last so it sees every link the earlier steps left dangling
+fn maintain(
+    settings: &PrivacySettings,
+    revoke_expired: impl FnOnce() -> ExternResult<u32>,
+    delete_older_than: impl FnOnce(i64) -> ExternResult<(u32, u32, u32)>,
+    anonymize: impl FnOnce() -> ExternResult<u32>,
+    repair_links: impl FnOnce() -> ExternResult<LinkAudit>,
+) -> ExternResult<MaintenanceReport> {
+    let permissions_revoked = revoke_expired()?;
+    
+    let retention_days = settings.activity_log_retention_days;
+    let (login_activities_deleted, dashboard_activities_deleted, oauth_activities_deleted) = if retention_days > 0 {
+        delete_older_than(retention_days)?
+    } else {
+        debug!("🧹 [MAINTENANCE] No retention period set, keeping all activity");
+        (0, 0, 0)
+    };
+    
+    let login_activities_anonymized = anonymize()?;
+    let links_repaired = repair_links()?;
+    
+    Ok(MaintenanceReport {
+        permissions_revoked,
+        login_activities_deleted,
+        dashboard_activities_deleted,
+        oauth_activities_deleted,
+        login_activities_anonymized,
+        links_repaired,
+    })
+}
+
+/// Walk from a record back through prev_action to the chain's first action (high-assurance reads)
+/// Returns false if any action fails to resolve, changes author, or breaks the action_seq sequence
+#[hdk_extern]
+pub fn verify_record_chain(action_hash: ActionHash) -> ExternResult<bool> {
+    Ok(chain_is_intact(&action_hash, |hash| must_get_action(hash.clone()).ok()))
+}
+
+/// Walk back from `action_hash` through prev_action, loading each action with `fetch`
+/// False as soon as an action is missing, has another author, or breaks the action_seq sequence
+fn chain_is_intact(
+    action_hash: &ActionHash,
+    mut fetch: impl FnMut(&ActionHash) -> Option<SignedActionHashed>,
+) -> bool {
+    let Some(start) = fetch(action_hash) else {
+        debug!("🩺 [AUDIT] Record {} could not be resolved", action_hash);
+        return false;
+    };
+    let author = start.action().author().clone();
+    let mut current = start;
+    let mut checked = 1;
+    
+    while let Some(prev_hash) = current.action().prev_action().cloned() {
+        let Some(prev) = fetch(&prev_hash) else {
+            debug!("🩺 [AUDIT] Chain break: {} could not be resolved", prev_hash);
+            return false;
+        };
+        if *prev.action().author() != author {
+            debug!("🩺 [AUDIT] Chain break: {} has a different author", prev_hash);
+            return false;
+        }
+        if prev.action().action_seq() + 1 != current.action().action_seq() {
+            debug!("🩺 [AUDIT] Chain break: action_seq gap before {}", current.action_address());
+            return false;
+        }
+        current = prev;
+        checked += 1;
+    }
+    
+    debug!("🩺 [AUDIT] Verified {} actions back from {}", checked, action_hash);
+    true
+}
+
+// ============================================================================
+// ACCOUNT DELETION - GDPR right to erasure
+// ============================================================================
+
+/// Per-type counts of entries removed by delete_account (same shape as LinkAudit)
+pub type DeletionReport = LinkAudit;
+
+/// Delete a record and every update reachable from it, skipping ones already deleted
+/// Returns true if anything was deleted
+fn delete_update_tree(root_hash: ActionHash) -> ExternResult<bool> {
+    let mut deleted_any = false;
+    let mut pending = vec![root_hash];
+    
+    while let Some(current_hash) = pending.pop() {
+        if let Some(Details::Record(record_details)) = get_details(current_hash.clone(), GetOptions::default())? {
+            for update in &record_details.updates {
+                pending.push(update.action_address().clone());
+            }
+            if record_details.deletes.is_empty() {
+                delete_entry(current_hash)?;
+                deleted_any = true;
+            }
+        }
+    }
+    
+    Ok(deleted_any)
+}
+
+/// Delete every entry (with its updates) and link of one AgentTo* link type
+/// Returns the number of entries deleted
+fn purge_link_type(link_type: LinkTypes) -> ExternResult<u32> {
+    let my_agent_pub_key = agent_info()?.agent_initial_pubkey;
+    
+    let links = get_links(
+        LinkQuery::try_new(my_agent_pub_key, link_type)?,
+        GetStrategy::default()
+    )?;
+    
+    let mut deleted_count = 0;
+    for link in links {
+        if let Ok(target_hash) = ActionHash::try_from(link.target.clone()) {
+            if delete_update_tree(target_hash)? {
+                deleted_count += 1;
+            }
+        }
+        delete_link(link.create_link_hash, GetOptions::default())?;
+    }
+    
+    Ok(deleted_count)
+}
+
+/// Creates not targeted by any of the deletes
+fn undeleted_creates(creates: Vec<ActionHash>, deletes: &[ActionHash]) -> Vec<ActionHash> {
+    creates
+        .into_iter()
+        .filter(|create_hash| !deletes.contains(create_hash))
+        .collect()
+}
+
+/// Delete PrivacySettings creates on the source chain that no link reaches any more
+/// (e.g., duplicates unlinked by compact_privacy_settings) so integrity allows a fresh create afterwards
+/// Returns the number of entries deleted
+fn purge_unlinked_privacy_settings() -> ExternResult<u32> {
+    let creates = query(
+        ChainQueryFilter::new()
+            .entry_type(EntryType::try_from(UnitEntryTypes::PrivacySettings)?)
+            .action_type(ActionType::Create)
+            .include_entries(false)
+    )?
+        .into_iter()
+        .map(|record| record.action_address().clone())
+        .collect();
+    let deletes: Vec<ActionHash> = query(ChainQueryFilter::new().action_type(ActionType::Delete))?
+        .into_iter()
+        .filter_map(|record| match record.action() {
+            Action::Delete(delete) => Some(delete.deletes_address.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let mut deleted_count = 0;
+    for create_hash in undeleted_creates(creates, &deletes) {
+        if delete_update_tree(create_hash)? {
+            deleted_count += 1;
+        }
+    }
+    Ok(deleted_count)
+}
+
+/// Delete all of the agent's private data and every AgentTo* link in one call
+/// Afterwards the agent starts fresh: create_default_privacy_settings succeeds again
+/// Idempotent - a repeat call finds nothing left and reports zeros
+#[hdk_extern]
+pub fn delete_account(_: ()) -> ExternResult<DeletionReport> {
+    let mut report = build_link_audit(purge_link_type)?;
+    let unlinked_settings = purge_unlinked_privacy_settings()?;
+    report.privacy_settings += unlinked_settings;
+    report.total += unlinked_settings;
+    
+    debug!("🧹 [ACCOUNT] Deleted {} entries for account erasure", report.total);
+    Ok(report)
+}
+
+// ============================================================================
+// STORAGE REPORT - How much is this agent storing?
+// ============================================================================
+
+/// Serialized byte size per entry type held by the agent
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct StorageReport {
+    pub user_profile_bytes: u64,
+    pub recovery_phrase_bytes: u64,
+    pub sessions_bytes: u64,
+    pub email_permissions_bytes: u64,
+    pub login_activity_bytes: u64,
+    pub dashboard_activity_bytes: u64,
+    pub oauth_activity_bytes: u64,
+    pub privacy_settings_bytes: u64,
+    pub analytics_ids_bytes: u64,
+    pub totp_config_bytes: u64,
+    pub profile_picture_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl StorageReport {
+    /// Sum of every per-type size (total_bytes itself is not included)
+    fn sum_of_types(&self) -> u64 {
+        self.user_profile_bytes
+            + self.recovery_phrase_bytes
+            + self.sessions_bytes
+            + self.email_permissions_bytes
+            + self.login_activity_bytes
+            + self.dashboard_activity_bytes
+            + self.oauth_activity_bytes
+            + self.privacy_settings_bytes
+            + self.analytics_ids_bytes
+            + self.totp_config_bytes
+            + self.profile_picture_bytes
+    }
+}
+
+/// Serialized size of the app entry held in a record
+fn record_entry_size(record: &Record) -> u64 {
+    match record.entry().as_option() {
+        Some(Entry::App(app_entry_bytes)) => app_entry_bytes.bytes().len() as u64,
+        _ => 0,
+    }
+}
+
+/// Sum of the serialized sizes of a list of entries
+fn entries_size<T>(entries: Vec<T>) -> ExternResult<u64>
+where
+    SerializedBytes: TryFrom<T, Error = SerializedBytesError>,
+{
+    let mut total = 0;
+    for entry in entries {
+        let bytes = SerializedBytes::try_from(entry).map_err(|e| wasm_error!(e))?;
+        total += bytes.bytes().len() as u64;
+    }
+    Ok(total)
+}
+
+/// Max activity items per type read when estimating storage (same cap as export_all_data)
+const STORAGE_SCAN_LIMIT: usize = 10000;
+
+/// Estimate how many bytes of private data this agent is storing, per entry type
+/// Sizes are the serialized entry lengths (latest version of each chain)
+#[hdk_extern]
+pub fn estimate_storage_bytes(_: ()) -> ExternResult<StorageReport> {
+    let mut report = StorageReport {
+        user_profile_bytes: get_user_profile(())?.as_ref().map(record_entry_size).unwrap_or(0),
+        recovery_phrase_bytes: get_recovery_phrase(())?.as_ref().map(record_entry_size).unwrap_or(0),
+        sessions_bytes: get_my_sessions(())?.iter().map(record_entry_size).sum(),
+        email_permissions_bytes: entries_size(get_email_permissions(())?)?,
+        login_activity_bytes: entries_size(load_login_activity(STORAGE_SCAN_LIMIT, 0)?)?,
+        dashboard_activity_bytes: entries_size(load_dashboard_activity(STORAGE_SCAN_LIMIT, 0)?)?,
+        oauth_activity_bytes: entries_size(load_oauth_activity(STORAGE_SCAN_LIMIT, 0)?)?,
+        privacy_settings_bytes: get_privacy_settings(())?.as_ref().map(record_entry_size).unwrap_or(0),
+        analytics_ids_bytes: entries_size(get_all_analytics_ids(())?)?,
+        totp_config_bytes: get_totp_config(())?.as_ref().map(record_entry_size).unwrap_or(0),
+        profile_picture_bytes: get_profile_picture(())?.as_ref().map(record_entry_size).unwrap_or(0),
+        total_bytes: 0,
+    };
+
+    report.total_bytes = report.sum_of_types();
+
+    debug!("💾 [STORAGE] Estimated {} bytes stored", report.total_bytes);
+    Ok(report)
+}
+
+/// Serialized bytes per activity type, largest first, so the UI can suggest which history to prune
+#[hdk_extern]
+pub fn estimate_activity_storage(_: ()) -> ExternResult<Vec<(String, u64)>> {
+    activity_storage(
+        load_login_activity(STORAGE_SCAN_LIMIT, 0)?,
+        load_dashboard_activity(STORAGE_SCAN_LIMIT, 0)?,
+        load_oauth_activity(STORAGE_SCAN_LIMIT, 0)?,
+    )
+}
+
+/// Serialized bytes of each activity type, largest first
+fn activity_storage(
+    logins: Vec<LoginActivity>,
+    dashboard_visits: Vec<DashboardActivity>,
+    oauth: Vec<OAuthActivity>,
+) -> ExternResult<Vec<(String, u64)>> {
+    let mut sizes = vec![
+        ("login_activity".to_string(), entries_size(logins)?),
+        ("dashboard_activity".to_string(), entries_size(dashboard_visits)?),
+        ("oauth_activity".to_string(), entries_size(oauth)?),
+    ];
+    sizes.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+    
+    Ok(sizes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping_answers_with_the_dna_version() {
+        assert_eq!(ping(()).unwrap(), "pong 1.12");
+    }
+
+    fn action_hash(byte: u8) -> ActionHash {
+        ActionHash::from_raw_36(vec![byte; 36])
+    }
+
+    fn author() -> AgentPubKey {
+        AgentPubKey::from_raw_36(vec![0xaa; 36])
+    }
+
+    fn signed(action: Action, hash: ActionHash) -> SignedActionHashed {
+        SignedActionHashed::with_presigned(ActionHashed::with_pre_hashed(action, hash), Signature([0; 64]))
+    }
+
+    /// A Create with hash `action_hash(byte)` at the given timestamp
+    fn signed_create(byte: u8, timestamp: i64) -> SignedActionHashed {
+        let create = Create {
+            author: author(),
+            timestamp: Timestamp::from_micros(timestamp),
+            action_seq: byte as u32,
+            prev_action: action_hash(0),
+            entry_type: EntryType::App(AppEntryDef::new(0.into(), 0.into(), EntryVisibility::Private)),
+            entry_hash: EntryHash::from_raw_36(vec![0xee; 36]),
+            weight: Default::default(),
+        };
+        signed(Action::Create(create), action_hash(byte))
+    }
+
+    /// A Delete with hash `action_hash(byte)` removing `action_hash(deletes)`
+    fn signed_delete(byte: u8, deletes: u8) -> SignedActionHashed {
+        let delete = Delete {
+            author: author(),
+            timestamp: Timestamp::from_micros(1_000),
+            action_seq: byte as u32,
+            prev_action: action_hash(0),
+            deletes_address: action_hash(deletes),
+            deletes_entry_address: EntryHash::from_raw_36(vec![0xee; 36]),
+            weight: Default::default(),
+        };
+        signed(Action::Delete(delete), action_hash(byte))
+    }
+
+    fn entry_details(actions: Vec<SignedActionHashed>, updates: Vec<SignedActionHashed>, deletes: Vec<SignedActionHashed>) -> Details {
+        Details::Entry(EntryDetails {
+            entry: Entry::Agent(author()),
+            actions,
+            rejected_actions: vec![],
+            deletes,
+            updates,
+            entry_dht_status: EntryDhtStatus::Live,
+        })
+    }
+
+    #[test]
+    fn entry_details_head_is_the_newest_live_action() {
+        let details = entry_details(
+            vec![signed_create(1, 100), signed_create(2, 300), signed_create(3, 200)],
+            vec![],
+            vec![signed_delete(9, 2)],
+        );
+        match chain_step(details) {
+            Some(ChainStep::Head(record)) => {
+                assert_eq!(record.action_address(), &action_hash(3));
+                assert_eq!(record.entry().as_option(), Some(&Entry::Agent(author())));
+            }
+            _ => panic!("expected the newest live action as the head"),
+        }
+    }
+
+    #[test]
+    fn updates_in_the_same_microsecond_tie_break_on_action_hash() {
+        let forward = vec![signed_create(4, 500), signed_create(7, 500), signed_create(2, 400)];
+        let reversed: Vec<SignedActionHashed> = forward.iter().rev().cloned().collect();
+        assert_eq!(newest_action(&forward).unwrap().action_address(), &action_hash(7));
+        assert_eq!(newest_action(&reversed).unwrap().action_address(), &action_hash(7));
+        assert!(newest_action(&[]).is_none());
+    }
+
+    #[test]
+    fn entry_details_follow_the_newest_update() {
+        let details = entry_details(
+            vec![signed_create(1, 100)],
+            vec![signed_create(4, 400), signed_create(5, 500)],
+            vec![],
+        );
+        match chain_step(details) {
+            Some(ChainStep::Next(next_hash)) => assert_eq!(next_hash, action_hash(5)),
+            _ => panic!("expected to follow the newest update"),
+        }
+    }
+
+    #[test]
+    fn entry_details_with_every_action_deleted_are_not_found() {
+        let details = entry_details(vec![signed_create(1, 100)], vec![], vec![signed_delete(9, 1)]);
+        assert!(chain_step(details).is_none());
+    }
+
+    const DAY: i64 = MICROS_PER_DAY;
+
+    fn record_details(deletes: Vec<SignedActionHashed>) -> Details {
+        Details::Record(RecordDetails {
+            record: Record::new(signed_create(1, 100), None),
+            validation_status: ValidationStatus::Valid,
+            deletes,
+            updates: vec![],
+        })
+    }
+
+    /// Record details for `action_hash(byte)` updated by the given actions
+    fn updated_record(byte: u8, updates: Vec<SignedActionHashed>) -> Details {
+        Details::Record(RecordDetails {
+            record: Record::new(signed_create(byte, byte as i64), None),
+            validation_status: ValidationStatus::Valid,
+            deletes: vec![],
+            updates,
+        })
+    }
+
+    #[test]
+    fn walk_update_chain_counts_every_hop_to_the_head() {
+        let chain = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(updated_record(1, vec![signed_create(2, 2)])),
+                2 => Some(updated_record(2, vec![signed_create(3, 3)])),
+                3 => Some(updated_record(3, vec![])),
+                _ => None,
+            })
+        };
+        let (head, path) = walk_update_chain(action_hash(1), chain).unwrap().unwrap();
+        assert_eq!(head.action_address(), &action_hash(3));
+        assert_eq!(path.len(), 3);
+
+        let (head, path) = walk_update_chain(action_hash(3), chain).unwrap().unwrap();
+        assert_eq!(head.action_address(), &action_hash(3));
+        assert_eq!(path.len(), 1);
+    }
+
+    #[test]
+    fn squash_deletes_each_action_the_chain_followed() {
+        // Where an action was updated twice (2 and 3) only the newest update is part of the chain
+        let chain = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(updated_record(1, vec![signed_create(2, 2), signed_create(3, 3)])),
+                3 => Some(updated_record(3, vec![signed_create(4, 4)])),
+                4 => Some(updated_record(4, vec![])),
+                _ => None,
+            })
+        };
+        let (head, path) = walk_update_chain(action_hash(1), chain).unwrap().unwrap();
+        assert_eq!(path, vec![action_hash(1), action_hash(3), action_hash(4)]);
+        assert_eq!(path.last(), Some(head.action_address()));
+    }
+
+    fn profile_link(target: u8) -> Link {
+        Link {
+            author: author(),
+            base: author().into(),
+            target: action_hash(target).into(),
+            timestamp: Timestamp::from_micros(0),
+            zome_index: 0.into(),
+            link_type: 0.into(),
+            tag: LinkTag::new(vec![]),
+            create_link_hash: action_hash(200),
+        }
+    }
+
+    #[test]
+    fn linked_read_follows_the_chain_with_the_given_fetch() {
+        // The network holds the whole chain; the local store only has the original record
+        let network = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(updated_record(1, vec![signed_create(2, 2)])),
+                2 => Some(updated_record(2, vec![])),
+                _ => None,
+            })
+        };
+        let local = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok((hash.get_raw_36()[0] == 1).then(|| updated_record(1, vec![signed_create(2, 2)])))
+        };
+        let unresolved = |_: &ActionHash| wasm_error!("unresolved");
+
+        let head = latest_from_first_link(&[profile_link(1)], network, unresolved).unwrap().unwrap();
+        assert_eq!(head.action_address(), &action_hash(2));
+        assert!(latest_from_first_link(&[profile_link(1)], local, unresolved).is_err());
+    }
+
+    #[test]
+    fn linked_read_without_a_link_is_none_and_with_a_dead_link_is_an_error() {
+        let nothing = |_: &ActionHash| -> ExternResult<Option<Details>> { Ok(None) };
+        let unresolved = |_: &ActionHash| wasm_error!("unresolved");
+        assert!(latest_from_first_link(&[], nothing, unresolved).unwrap().is_none());
+        assert!(latest_from_first_link(&[profile_link(1)], nothing, unresolved).is_err());
+    }
+
+    #[test]
+    fn only_the_resolved_head_is_current() {
+        let chain = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(updated_record(1, vec![signed_create(2, 2)])),
+                2 => Some(updated_record(2, vec![])),
+                _ => None,
+            })
+        };
+        let unresolved = |_: &ActionHash| wasm_error!("unresolved");
+        let head = latest_from_first_link(&[profile_link(1)], chain, unresolved).unwrap();
+
+        assert!(is_chain_head(head.as_ref(), &action_hash(2)));
+        assert!(!is_chain_head(head.as_ref(), &action_hash(1)));
+        assert!(!is_chain_head(None, &action_hash(2)));
+    }
+
+    #[test]
+    fn walk_update_chain_reports_a_missing_start_and_a_broken_chain() {
+        let broken = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(updated_record(1, vec![signed_create(2, 2)])),
+                _ => None,
+            })
+        };
+        assert!(walk_update_chain(action_hash(5), broken).unwrap().is_none());
+        assert!(walk_update_chain(action_hash(1), broken).is_err());
+    }
+
+    #[test]
+    fn link_audit_counts_every_link_type_once() {
+        let mut seen = Vec::new();
+        let audit = build_link_audit(|link_type| {
+            seen.push(format!("{:?}", link_type));
+            Ok(seen.len() as u32)
+        })
+        .unwrap();
+
+        let distinct: std::collections::BTreeSet<&String> = seen.iter().collect();
+        assert_eq!(seen.len(), 12);
+        assert_eq!(distinct.len(), 12);
+        assert_eq!(audit.profile, 1);
+        assert_eq!(audit.import_checkpoint, 12);
+        assert_eq!(audit.total, (1..=12).sum::<u32>());
+    }
+
+    #[test]
+    fn only_a_seen_delete_marks_a_link_target_deleted() {
+        assert_eq!(link_target(Some(record_details(vec![]))), LinkTarget::Live);
+        assert_eq!(link_target(Some(record_details(vec![signed_delete(9, 1)]))), LinkTarget::Deleted);
+        assert_eq!(link_target(Some(entry_details(vec![signed_create(1, 100)], vec![], vec![]))), LinkTarget::Live);
+        assert_eq!(
+            link_target(Some(entry_details(vec![signed_create(1, 100)], vec![], vec![signed_delete(9, 1)]))),
+            LinkTarget::Deleted
+        );
+    }
+
+    #[test]
+    fn unfetchable_link_target_is_missing_not_deleted() {
+        assert_eq!(link_target(None), LinkTarget::Missing);
+        assert_eq!(link_target(Some(entry_details(vec![], vec![], vec![]))), LinkTarget::Missing);
+    }
+
+    /// A record at `action_hash(byte)` holding the given entry
+    fn record_with(byte: u8, entry: Option<Entry>) -> Record {
+        Record::new(signed_create(byte, byte as i64), entry)
+    }
+
+    fn app_entry(bytes: SerializedBytes) -> Option<Entry> {
+        Some(Entry::App(AppEntryBytes(bytes)))
+    }
+
+    fn settings_head(byte: u8, updated_at: i64) -> (u8, Record, PrivacySettings) {
+        let mut settings = default_privacy_settings(1);
+        settings.updated_at = updated_at;
+        (byte, record_with(byte, None), settings)
+    }
+
+    #[test]
+    fn duplicate_settings_resolve_to_the_newest_chain() {
+        let mut heads = vec![settings_head(1, 100), settings_head(2, 300), settings_head(3, 200)];
+        sort_settings_newest_first(&mut heads);
+        let order: Vec<u8> = heads.iter().map(|(byte, _, _)| *byte).collect();
+        assert_eq!(order, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn duplicate_settings_with_the_same_updated_at_tie_break_on_hash() {
+        let mut heads = vec![settings_head(4, 100), settings_head(9, 100), settings_head(6, 100)];
+        sort_settings_newest_first(&mut heads);
+        let order: Vec<u8> = heads.iter().map(|(byte, _, _)| *byte).collect();
+        assert_eq!(order, vec![9, 6, 4]);
+    }
+
+    #[test]
+    fn export_with_duplicate_settings_links_picks_the_newest() {
+        let mut older = settings_head(1, 100);
+        older.2.default_page_size = 10;
+        let mut newest = settings_head(2, 300);
+        newest.2.default_page_size = 50;
+
+        let (record, exported) = newest_settings(vec![older, newest, settings_head(3, 200)]).unwrap();
+        assert_eq!(record.action_address(), &action_hash(2));
+        assert_eq!(exported.updated_at, 300);
+        assert_eq!(exported.default_page_size, 50);
+        assert!(newest_settings(Vec::<(u8, Record, PrivacySettings)>::new()).is_none());
+    }
+
+    #[test]
+    fn decoded_profile_comes_with_its_record() {
+        let record = record_with(1, app_entry(SerializedBytes::try_from(profile(100, 200)).unwrap()));
+        let (decoded_record, decoded) = decode_profile_record(record).unwrap();
+        assert_eq!(decoded_record.action_address(), &action_hash(1));
+        assert_eq!(decoded, profile(100, 200));
+    }
+
+    #[test]
+    fn record_without_a_profile_entry_does_not_decode() {
+        assert!(decode_profile_record(record_with(1, None)).is_err());
+        let other = record_with(1, app_entry(SerializedBytes::try_from(checkpoint(1, 1, 0)).unwrap()));
+        assert!(decode_profile_record(other).is_err());
+    }
+
+    fn profile(created_at: i64, updated_at: i64) -> UserProfile {
+        UserProfile {
+            encrypted_email: "ZW1haWw=".into(),
+            nonce: "bm9uY2U=".into(),
+            salt: "c2FsdA==".into(),
+            tag: "dGFn".into(),
+            username: None,
+            display_name: "Ada".into(),
+            created_at,
+            updated_at,
+        }
+    }
+
+    fn recovery_phrase(salt: &str) -> RecoveryPhrase {
+        RecoveryPhrase {
+            encrypted_mnemonic: "bW5lbW9uaWM=".into(),
+            nonce: "bm9uY2U=".into(),
+            salt: salt.into(),
+            tag: "dGFn".into(),
+            verified: true,
+            created_at: 1,
+        }
+    }
+
+    fn recovery_record(byte: u8, verified: bool) -> Record {
+        let mut recovery = recovery_phrase("c2FsdA==");
+        recovery.verified = verified;
+        record_with(byte, app_entry(SerializedBytes::try_from(recovery).unwrap()))
+    }
+
+    #[test]
+    fn login_bundle_carries_the_profile_head_and_verified_status() {
+        let profile_head = record_with(2, app_entry(SerializedBytes::try_from(profile(1, 2)).unwrap()));
+        let bundle = login_bundle(Some(profile_head), Some(recovery_record(3, true))).unwrap();
+        assert_eq!(bundle.profile.map(|record| record.action_address().clone()), Some(action_hash(2)));
+        assert_eq!(bundle.recovery_phrase_verified, Some(true));
+
+        let unverified = login_bundle(None, Some(recovery_record(4, false))).unwrap();
+        assert!(unverified.profile.is_none());
+        assert_eq!(unverified.recovery_phrase_verified, Some(false));
+        assert_eq!(login_bundle(None, None).unwrap().recovery_phrase_verified, None);
+    }
+
+    /// Record details for a recovery phrase created at `created_at`, stored at `action_hash(byte)`
+    fn recovery_details(byte: u8, created_at: i64) -> Details {
+        let recovery = RecoveryPhrase { created_at, ..recovery_phrase("c2FsdA==") };
+        Details::Record(RecordDetails {
+            record: record_with(byte, app_entry(SerializedBytes::try_from(recovery).unwrap())),
+            validation_status: ValidationStatus::Valid,
+            deletes: vec![],
+            updates: vec![],
+        })
+    }
+
+    #[test]
+    fn compacting_duplicate_recovery_links_keeps_the_newest() {
+        let store = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(recovery_details(1, 100)),
+                2 => Some(recovery_details(2, 300)),
+                3 => Some(recovery_details(3, 200)),
+                _ => None,
+            })
+        };
+        let links = vec![session_link(1), session_link(2), session_link(3)];
+        let (newest, stale) = newest_recovery_phrase(links, store).unwrap().unwrap();
+        assert_eq!(newest.action_address(), &action_hash(2));
+        assert_eq!(stale, vec![action_hash(203), action_hash(201)]);
+
+        // With the stale links gone the next read sees a single link and nothing left to compact
+        let (newest, stale) = newest_recovery_phrase(vec![session_link(2)], store).unwrap().unwrap();
+        assert_eq!(newest.action_address(), &action_hash(2));
+        assert!(stale.is_empty());
+    }
+
+    #[test]
+    fn recovery_phrases_with_the_same_created_at_have_a_stable_winner() {
+        let store = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(Some(recovery_details(hash.get_raw_36()[0], 100)))
+        };
+        for links in [vec![session_link(3), session_link(8)], vec![session_link(8), session_link(3)]] {
+            let (newest, stale) = newest_recovery_phrase(links, store).unwrap().unwrap();
+            assert_eq!(newest.action_address(), &action_hash(8));
+            assert_eq!(stale, vec![action_hash(203)]);
+        }
+    }
+
+    #[test]
+    fn recovery_phrase_created_at_for_present_and_absent_phrases() {
+        assert_eq!(recovery_phrase_created_at(Some(recovery_record(1, true))).unwrap(), Some(1));
+        assert_eq!(recovery_phrase_created_at(None).unwrap(), None);
+    }
+
+    #[test]
+    fn profile_deletion_is_blocked_by_an_unverified_phrase_unless_forced() {
+        let unverified = || Ok(Some(RecoveryPhrase { verified: false, ..recovery_phrase("c2FsdA==") }));
+        assert!(ensure_recovery_phrase_verified(false, unverified).is_err());
+        assert!(ensure_recovery_phrase_verified(true, unverified).is_ok());
+        assert!(ensure_recovery_phrase_verified(false, || Ok(Some(recovery_phrase("c2FsdA==")))).is_ok());
+        assert!(ensure_recovery_phrase_verified(false, || Ok(None)).is_ok());
+        // A forced delete doesn't read the phrase, so an unreadable one can't block it
+        assert!(ensure_recovery_phrase_verified(true, || Err(wasm_error!("unreadable"))).is_ok());
+    }
+
+    #[test]
+    fn login_bundle_with_a_malformed_recovery_phrase_is_an_error() {
+        assert!(login_bundle(None, Some(record_with(5, None))).is_err());
+    }
+
+    fn reencrypt_input(recovery_salt: &str) -> ReencryptAllInput {
+        let mut new_profile = profile(1, 2);
+        new_profile.salt = "bmV3LXNhbHQ=".into();
+        ReencryptAllInput { new_profile, new_recovery: recovery_phrase(recovery_salt) }
+    }
+
+    /// Runs apply_reencryption, returning the result and the salts each write received
+    fn reencrypt(input: ReencryptAllInput, has_profile: bool, has_recovery: bool) -> (bool, Option<String>, Option<String>) {
+        let mut profile_salt = None;
+        let mut recovery_salt = None;
+        let result = apply_reencryption(
+            input,
+            has_profile,
+            has_recovery,
+            |profile| {
+                profile_salt = Some(profile.salt);
+                Ok(record_with(1, None))
+            },
+            |recovery| {
+                recovery_salt = Some(recovery.salt);
+                Ok(record_with(2, None))
+            },
+        );
+        (result.is_ok(), profile_salt, recovery_salt)
+    }
+
+    #[test]
+    fn reencrypt_all_writes_the_new_material_to_both_records() {
+        let (ok, profile_salt, recovery_salt) = reencrypt(reencrypt_input("bmV3LXNhbHQ="), true, true);
+        assert!(ok);
+        assert_eq!(profile_salt.as_deref(), Some("bmV3LXNhbHQ="));
+        assert_eq!(recovery_salt.as_deref(), Some("bmV3LXNhbHQ="));
+    }
+
+    #[test]
+    fn reencrypt_all_writes_neither_record_unless_both_can_be_written() {
+        assert_eq!(reencrypt(reencrypt_input("bmV3LXNhbHQ="), true, false), (false, None, None));
+        assert_eq!(reencrypt(reencrypt_input("bmV3LXNhbHQ="), false, true), (false, None, None));
+        // Bad recovery material is caught before the profile is written
+        assert_eq!(reencrypt(reencrypt_input("not base64!"), true, true), (false, None, None));
+    }
+
+    #[test]
+    fn profile_changed_since_a_cached_time_only_after_an_update() {
+        let cached_at = 200;
+        let original = profile(100, 100);
+        assert!(!profile_updated_after(Some(&original), cached_at));
+
+        let updated = UserProfile { display_name: "Ada L".into(), updated_at: 300, ..original };
+        assert!(profile_updated_after(Some(&updated), cached_at));
+        assert!(!profile_updated_after(Some(&updated), 300));
+        assert!(!profile_updated_after(None, cached_at));
+    }
+
+    #[test]
+    fn floor_to_day_snaps_to_utc_midnight() {
+        assert_eq!(floor_to_day(0), 0);
+        assert_eq!(floor_to_day(DAY - 1), 0);
+        assert_eq!(floor_to_day(DAY), DAY);
+        assert_eq!(floor_to_day(3 * DAY + 12_345), 3 * DAY);
+    }
+
+    #[test]
+    fn floor_to_day_floors_negative_timestamps_downward() {
+        assert_eq!(floor_to_day(-1), -DAY);
+        assert_eq!(floor_to_day(-DAY), -DAY);
+        assert_eq!(floor_to_day(-DAY - 1), -2 * DAY);
+    }
+
+    #[test]
+    fn login_counts_bucket_by_utc_day_within_the_range() {
+        let day_one = 20_000 * DAY;
+        let timestamps = vec![
+            day_one - 1,              // before the range
+            day_one,
+            day_one + DAY - 1,
+            day_one + 2 * DAY + 5,    // day two has no logins
+            day_one + 2 * DAY + 9,
+            day_one + 2 * DAY + 10,
+            day_one + 3 * DAY,        // end is exclusive
+        ];
+        assert_eq!(
+            count_by_day(timestamps, day_one, day_one + 3 * DAY),
+            vec![(day_one, 2), (day_one + 2 * DAY, 3)]
+        );
+        assert!(count_by_day(vec![], day_one, day_one + DAY).is_empty());
+    }
+
+    fn tracked_login(timestamp: i64) -> LoginActivity {
+        LoginActivity {
+            ip_address: Some("203.0.113.1".into()),
+            user_agent: Some("Firefox".into()),
+            region: Some("EU".into()),
+            ..login(timestamp)
+        }
+    }
+
+    fn login_in(region: Option<&str>, timestamp: i64) -> LoginActivity {
+        LoginActivity { region: region.map(str::to_string), ..login(timestamp) }
+    }
+
+    #[test]
+    fn logins_by_region_match_case_insensitively_newest_first() {
+        let activities = vec![login_in(Some("US"), 1), login_in(Some("de"), 2), login_in(Some(" us "), 3), login_in(None, 4)];
+        let timestamps: Vec<i64> = logins_in_region(activities.clone(), "us").iter().map(|a| a.timestamp).collect();
+        assert_eq!(timestamps, vec![3, 1]);
+        assert_eq!(logins_in_region(activities.clone(), "DE").len(), 1);
+        assert!(logins_in_region(activities, "fr").is_empty());
+    }
+
+    #[test]
+    fn region_is_only_stored_while_tracking_is_on() {
+        let mut tracked = login_in(Some("US"), 1);
+        drop_untracked_region(&mut tracked, true);
+        assert_eq!(tracked.region.as_deref(), Some("US"));
+
+        let mut untracked = login_in(Some("US"), 1);
+        drop_untracked_region(&mut untracked, false);
+        assert_eq!(untracked.region, None);
+        assert!(logins_in_region(vec![untracked], "us").is_empty());
+    }
+
+    #[test]
+    fn privacy_audit_flags_historical_data_that_settings_no_longer_allow() {
+        let activities = vec![tracked_login(1), login(2), LoginActivity { user_agent: None, ..tracked_login(3) }];
+        let mut settings = default_privacy_settings(1);
+        let audit = privacy_audit(&settings, &activities);
+        assert!(audit.compliant);
+        assert_eq!(audit.login_activities_checked, 3);
+
+        settings.track_ip_address = false;
+        settings.track_user_agent = false;
+        let audit = privacy_audit(&settings, &activities);
+        assert!(!audit.compliant);
+        assert_eq!(audit.ip_address_violations, 2);
+        assert_eq!(audit.user_agent_violations, 1);
+    }
+
+    #[test]
+    fn anonymizing_noncompliant_activity_makes_the_audit_pass() {
+        let mut settings = default_privacy_settings(1);
+        settings.track_ip_address = false;
+        settings.track_region = false;
+        let activities = vec![tracked_login(1), login(2)];
+
+        let remediated: Vec<LoginActivity> = activities
+            .iter()
+            .map(|activity| anonymized_activity(&settings, activity.clone()).unwrap_or_else(|| activity.clone()))
+            .collect();
+        assert!(anonymized_activity(&settings, login(2)).is_none());
+        assert_eq!(remediated[0].ip_address, None);
+        assert_eq!(remediated[0].region, None);
+        assert_eq!(remediated[0].user_agent.as_deref(), Some("Firefox"));
+        assert!(privacy_audit(&settings, &remediated).compliant);
+    }
+
+    /// Stale data of every kind run_maintenance cleans up
+    struct MaintenanceStore {
+        permissions: Vec<EmailPermission>,
+        logins: Vec<LoginActivity>,
+        link_targets: Vec<LinkTarget>,
+    }
+
+    fn run_maintenance_on(store: &std::cell::RefCell<MaintenanceStore>, settings: &PrivacySettings, now: i64) -> MaintenanceReport {
+        maintain(
+            settings,
+            || {
+                let mut revoked = 0;
+                for permission in store.borrow_mut().permissions.iter_mut() {
+                    if permission.granted && !is_permission_active(permission, now) {
+                        apply_revoke(permission, now);
+                        revoked += 1;
+                    }
+                }
+                Ok(revoked)
+            },
+            |retention_days| {
+                let cutoff = retention_cutoff(now, retention_days)?;
+                let mut store = store.borrow_mut();
+                let before = store.logins.len();
+                store.logins.retain(|activity| activity.created_at >= cutoff);
+                // The deleted entries' links are left for repair to find
+                let deleted = before - store.logins.len();
+                store.link_targets.extend(std::iter::repeat_n(LinkTarget::Deleted, deleted));
+                Ok((deleted as u32, 0, 0))
+            },
+            || {
+                let mut store = store.borrow_mut();
+                let mut anonymized = 0;
+                for activity in store.logins.iter_mut() {
+                    if let Some(stripped) = anonymized_activity(settings, activity.clone()) {
+                        *activity = stripped;
+                        anonymized += 1;
+                    }
+                }
+                Ok(anonymized)
+            },
+            || {
+                let mut store = store.borrow_mut();
+                let before = store.link_targets.len();
+                store.link_targets.retain(|target| *target != LinkTarget::Deleted);
+                let repaired = (before - store.link_targets.len()) as u32;
+                Ok(LinkAudit { login_activity: repaired, total: repaired, ..Default::default() })
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn maintenance_remediates_every_kind_of_stale_data() {
+        let now = 100 * DAY;
+        let mut settings = default_privacy_settings(1);
+        settings.activity_log_retention_days = 30;
+        settings.track_ip_address = false;
+
+        let store = std::cell::RefCell::new(MaintenanceStore {
+            permissions: vec![permission("billing", true, Some(now - 1)), permission("support", true, None)],
+            logins: vec![tracked_login(10 * DAY), tracked_login(90 * DAY)],
+            link_targets: vec![LinkTarget::Live, LinkTarget::Deleted],
+        });
+        let report = run_maintenance_on(&store, &settings, now);
+
+        assert_eq!(report.permissions_revoked, 1);
+        assert_eq!(report.login_activities_deleted, 1);
+        assert_eq!(report.login_activities_anonymized, 1);
+        // The link left dangling before the run and the one its retention delete left
+        assert_eq!(report.links_repaired.total, 2);
+
+        let store = store.into_inner();
+        assert!(store.permissions.iter().all(|permission| !permission.granted || is_permission_active(permission, now)));
+        assert_eq!(store.logins.len(), 1);
+        assert!(privacy_audit(&settings, &store.logins).compliant);
+        assert_eq!(store.link_targets, vec![LinkTarget::Live]);
+    }
+
+    #[test]
+    fn maintenance_without_a_retention_period_keeps_all_activity() {
+        let mut settings = default_privacy_settings(1);
+        settings.activity_log_retention_days = 0;
+        let store = std::cell::RefCell::new(MaintenanceStore {
+            permissions: vec![],
+            logins: vec![login(1), login(2)],
+            link_targets: vec![],
+        });
+        let report = run_maintenance_on(&store, &settings, 100 * DAY);
+        assert_eq!(report.login_activities_deleted, 0);
+        assert_eq!(store.into_inner().logins.len(), 2);
+    }
+
+    #[test]
+    fn retention_cutoff_counts_whole_days_back_from_today() {
+        let now = 10 * DAY + 5_000;
+        assert_eq!(retention_cutoff(now, 0).unwrap(), 10 * DAY);
+        assert_eq!(retention_cutoff(now, 3).unwrap(), 7 * DAY);
+        // Exactly at midnight the current day is still today
+        assert_eq!(retention_cutoff(10 * DAY, 1).unwrap(), 9 * DAY);
+        assert_eq!(retention_cutoff(DAY / 2, 2).unwrap(), -2 * DAY);
+    }
+
+    #[test]
+    fn retention_cutoff_rejects_negative_and_overflowing_days() {
+        assert!(retention_cutoff(10 * DAY, -1).is_err());
+        assert!(retention_cutoff(10 * DAY, i64::MAX).is_err());
+        assert!(retention_cutoff(10 * DAY, i64::MAX / MICROS_PER_DAY + 1).is_err());
+        // Fits in the multiplication but not once subtracted from today
+        assert!(retention_cutoff(-10 * DAY, i64::MAX / MICROS_PER_DAY).is_err());
+    }
+
+    #[test]
+    fn new_granted_permission_is_active_and_stamped() {
+        let permission = new_granted_permission(
+            "billing".into(), "Invoices".into(), Some(500), PermissionCategory::Transactional, false, 100,
+        );
+        assert!(permission.granted);
+        assert_eq!(permission.granted_at, Some(100));
+        assert_eq!(permission.last_used_at, None);
+        assert_eq!(permission.expires_at, Some(500));
+        assert_eq!(permission.category, PermissionCategory::Transactional);
+        assert!(is_permission_active(&permission, 499));
+        assert!(!is_permission_active(&permission, 500));
+    }
+
+    #[test]
+    fn revoke_then_regrant_clears_revoked_at() {
+        let mut permission = new_granted_permission(
+            "billing".into(), "Invoices".into(), None, PermissionCategory::Other, true, 100,
+        );
+        assert_eq!(permission.last_used_at, Some(100));
+
+        apply_revoke(&mut permission, 200);
+        assert!(!permission.granted);
+        assert_eq!(permission.revoked_at, Some(200));
+        assert_eq!(permission.updated_at, 200);
+        assert!(!is_permission_active(&permission, 200));
+
+        apply_grant(&mut permission, None, false, 300);
+        assert!(permission.granted);
+        assert_eq!(permission.granted_at, Some(300));
+        assert_eq!(permission.revoked_at, None);
+        assert_eq!(permission.last_used_at, Some(100));
+        assert_eq!(permission.created_at, 100);
+    }
+
+    #[test]
+    fn injected_clock_sets_exact_timestamps() {
+        // 2024-01-01T00:00:00Z in microseconds
+        const FIXED_NOW: i64 = 1_704_067_200_000_000;
+        const LATER: i64 = FIXED_NOW + 60_000_000;
+
+        let settings = default_privacy_settings(FIXED_NOW);
+        assert_eq!(settings.created_at, FIXED_NOW);
+        assert_eq!(settings.updated_at, FIXED_NOW);
+
+        let mut permission = new_granted_permission(
+            "billing".into(), "Invoices".into(), None, PermissionCategory::Other, false, FIXED_NOW,
+        );
+        assert_eq!(permission.created_at, FIXED_NOW);
+        assert_eq!(permission.updated_at, FIXED_NOW);
+        assert_eq!(permission.granted_at, Some(FIXED_NOW));
+
+        apply_grant(&mut permission, Some(LATER + 1), true, LATER);
+        assert_eq!(permission.created_at, FIXED_NOW);
+        assert_eq!(permission.updated_at, LATER);
+        assert_eq!(permission.granted_at, Some(LATER));
+        assert_eq!(permission.last_used_at, Some(LATER));
+        assert_eq!(permission.expires_at, Some(LATER + 1));
+    }
+
+    fn grant_input(category: Option<PermissionCategory>) -> GrantPermissionInput {
+        GrantPermissionInput {
+            service_name: "billing".into(),
+            purpose: "Invoices".into(),
+            expires_in_seconds: None,
+            category,
+        }
+    }
+
+    #[test]
+    fn grant_and_use_sets_granted_and_last_used_in_one_entry() {
+        let created = granted_permission(None, "billing".into(), grant_input(None), None, true, 100);
+        assert!(created.granted);
+        assert_eq!(created.granted_at, Some(100));
+        assert_eq!(created.last_used_at, Some(100));
+        assert_eq!(created.category, PermissionCategory::Other);
+
+        let revoked = permission("billing", false, None);
+        let regranted = granted_permission(Some(revoked), "billing".into(), grant_input(None), None, true, 300);
+        assert!(regranted.granted);
+        assert_eq!(regranted.revoked_at, None);
+        assert_eq!(regranted.last_used_at, Some(300));
+        assert_eq!(regranted.created_at, 100);
+    }
+
+    #[test]
+    fn plain_grant_leaves_last_used_unset_and_keeps_the_category() {
+        let mut existing = permission("billing", true, None);
+        existing.category = PermissionCategory::Transactional;
+        let regranted = granted_permission(Some(existing), "billing".into(), grant_input(None), None, false, 300);
+        assert_eq!(regranted.last_used_at, None);
+        assert_eq!(regranted.category, PermissionCategory::Transactional);
+
+        let recategorized = granted_permission(
+            Some(regranted), "billing".into(), grant_input(Some(PermissionCategory::Marketing)), None, false, 400,
+        );
+        assert_eq!(recategorized.category, PermissionCategory::Marketing);
+    }
+
+    #[test]
+    fn permission_expiry_adds_seconds_to_now() {
+        assert_eq!(permission_expiry(1_000, None).unwrap(), None);
+        assert_eq!(permission_expiry(1_000, Some(60)).unwrap(), Some(60_001_000));
+    }
+
+    #[test]
+    fn permission_expiry_rejects_non_positive_and_overflowing_durations() {
+        assert!(permission_expiry(1_000, Some(0)).is_err());
+        assert!(permission_expiry(1_000, Some(-60)).is_err());
+        assert!(permission_expiry(1_000, Some(i64::MAX)).is_err());
+        assert!(permission_expiry(i64::MAX - 10, Some(1)).is_err());
+    }
+
+    fn permission(service_name: &str, granted: bool, expires_at: Option<i64>) -> EmailPermission {
+        let mut permission = new_granted_permission(
+            service_name.into(), "Notifications".into(), expires_at, PermissionCategory::Other, false, 100,
+        );
+        if !granted {
+            apply_revoke(&mut permission, 200);
+        }
+        permission
+    }
+
+    #[test]
+    fn every_category_survives_an_entry_round_trip() {
+        for category in [
+            PermissionCategory::Transactional,
+            PermissionCategory::Security,
+            PermissionCategory::Marketing,
+            PermissionCategory::Support,
+            PermissionCategory::Other,
+        ] {
+            let mut stored = permission("billing", true, None);
+            stored.category = category;
+            let bytes = SerializedBytes::try_from(stored.clone()).unwrap();
+            assert_eq!(EmailPermission::try_from(bytes).unwrap(), stored);
+        }
+    }
+
+    /// EmailPermission as written before categories existed
+    #[derive(Serialize, Deserialize, SerializedBytes, Debug)]
+    struct UncategorizedPermission {
+        service_name: String,
+        purpose: String,
+        granted: bool,
+        granted_at: Option<i64>,
+        revoked_at: Option<i64>,
+        last_used_at: Option<i64>,
+        expires_at: Option<i64>,
+        created_at: i64,
+        updated_at: i64,
+    }
+
+    #[test]
+    fn permission_stored_before_categories_reads_as_other() {
+        let legacy = UncategorizedPermission {
+            service_name: "billing".into(),
+            purpose: "Invoices".into(),
+            granted: true,
+            granted_at: Some(100),
+            revoked_at: None,
+            last_used_at: None,
+            expires_at: None,
+            created_at: 100,
+            updated_at: 100,
+        };
+        let permission = EmailPermission::try_from(SerializedBytes::try_from(legacy).unwrap()).unwrap();
+        assert_eq!(permission.category, PermissionCategory::Other);
+        assert_eq!(permission.service_name, "billing");
+    }
+
+    #[test]
+    fn permissions_by_category_keep_only_that_category() {
+        let categorized = |service_name: &str, category| EmailPermission { category, ..permission(service_name, true, None) };
+        let permissions = vec![
+            categorized("newsletter", PermissionCategory::Marketing),
+            categorized("billing", PermissionCategory::Transactional),
+            categorized("offers", PermissionCategory::Marketing),
+        ];
+        
+        let marketing: Vec<String> = permissions_in_category(permissions.clone(), PermissionCategory::Marketing)
+            .into_iter()
+            .map(|permission| permission.service_name)
+            .collect();
+        assert_eq!(marketing, vec!["newsletter".to_string(), "offers".to_string()]);
+        assert!(permissions_in_category(permissions, PermissionCategory::Security).is_empty());
+    }
+
+    /// Record details for a permission stored at `action_hash(byte)`
+    fn permission_details(byte: u8, service_name: &str, deletes: Vec<SignedActionHashed>) -> Details {
+        let bytes = SerializedBytes::try_from(permission(service_name, true, None)).unwrap();
+        Details::Record(RecordDetails {
+            record: record_with(byte, app_entry(bytes)),
+            validation_status: ValidationStatus::Valid,
+            deletes,
+            updates: vec![],
+        })
+    }
+
+    #[test]
+    fn deleted_permission_is_not_returned_while_its_link_remains() {
+        let store = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                1 => Some(permission_details(1, "billing", vec![])),
+                2 => Some(permission_details(2, "marketing", vec![signed_delete(9, 2)])),
+                _ => None,
+            })
+        };
+        let heads = permission_heads_from(vec![profile_link(1), profile_link(2)], store).unwrap();
+        let services: Vec<&str> = heads.iter().map(|(_, p)| p.service_name.as_str()).collect();
+        assert_eq!(services, vec!["billing"]);
+        assert_eq!(heads[0].0, action_hash(1));
+    }
+
+    #[test]
+    fn require_permission_finds_the_granted_head() {
+        let heads = vec![
+            (action_hash(1), permission("support", true, None)),
+            (action_hash(2), permission("billing", true, Some(1_000))),
+        ];
+        let (head_hash, found) = find_active_permission(heads, "billing", 999).unwrap();
+        assert_eq!(head_hash, action_hash(2));
+        assert_eq!(found.service_name, "billing");
+    }
+
+    #[test]
+    fn require_permission_denies_revoked_expired_and_unknown_services() {
+        let heads = vec![
+            (action_hash(1), permission("billing", false, None)),
+            (action_hash(2), permission("security", true, Some(1_000))),
+        ];
+        assert!(find_active_permission(heads.clone(), "billing", 500).is_none());
+        assert!(find_active_permission(heads.clone(), "security", 1_000).is_none());
+        assert!(find_active_permission(heads, "marketing", 500).is_none());
+    }
+
+    #[test]
+    fn export_permission_returns_the_full_record_for_a_present_service() {
+        let permissions = vec![permission("billing", true, Some(500)), permission("marketing", false, None)];
+        let exported = permission_for_service(permissions.clone(), " Marketing ").unwrap();
+        assert_eq!(exported, permissions[1]);
+        assert_eq!(permission_for_service(permissions, "billing").unwrap().expires_at, Some(500));
+    }
+
+    #[test]
+    fn export_permission_for_a_missing_service_is_an_error() {
+        let permissions = vec![permission("billing", true, None)];
+        let error = permission_for_service(permissions, "support").unwrap_err();
+        assert!(format!("{:?}", error).contains("support"));
+        assert!(permission_for_service(vec![], "billing").is_err());
+    }
+
+    #[test]
+    fn permission_statuses_mix_granted_and_ungranted_services() {
+        let permissions = vec![
+            permission("billing", true, None),
+            permission("marketing", false, None),
+            permission("security", true, Some(1_000)),
+        ];
+        let services = vec!["Billing ".to_string(), "marketing".into(), "security".into(), "support".into()];
+        assert_eq!(permission_statuses(services, &permissions, 1_000), vec![
+            ("Billing ".to_string(), true),
+            ("marketing".to_string(), false),
+            ("security".to_string(), false),
+            ("support".to_string(), false),
+        ]);
+    }
+
+    #[test]
+    fn permissions_summary_counts_expired_grants_separately() {
+        let now = 1_000;
+        let permissions = vec![
+            permission("billing", true, None),
+            permission("support", true, Some(now + 1)),
+            permission("alerts", true, Some(now)),
+            permission("news", false, None),
+        ];
+        let summary = summarize_permissions(&permissions, now);
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.granted, 2);
+        assert_eq!(summary.expired, 1);
+        assert_eq!(summary.revoked, 1);
+        assert_eq!(summary.unused_30d, 2);
+    }
+
+    #[test]
+    fn batch_usage_only_marks_active_requested_services() {
+        let heads = vec![
+            (action_hash(1), permission("billing", true, None)),
+            (action_hash(2), permission("marketing", false, None)),
+            (action_hash(3), permission("Support", true, Some(2_000))),
+            (action_hash(4), permission("security", true, None)),
+            (action_hash(5), permission("newsletter", true, Some(500))),
+        ];
+        let services = vec!["billing".to_string(), "marketing".into(), "support".into(), "newsletter".into()];
+        let marked: Vec<ActionHash> = permissions_to_mark_used(heads, &services, 1_000)
+            .into_iter()
+            .map(|(head_hash, _)| head_hash)
+            .collect();
+        assert_eq!(marked, vec![action_hash(1), action_hash(3)]);
+    }
+
+    #[test]
+    fn permission_history_has_one_version_per_grant_and_revoke() {
+        // grant (1) -> revoke (2) -> grant (3) -> revoke (4), each updating the previous head
+        let mut chain = std::collections::HashMap::new();
+        let mut current = permission("billing", true, None);
+        for byte in 1..=4u8 {
+            if byte > 1 {
+                let now = byte as i64 * 100;
+                if current.granted {
+                    apply_revoke(&mut current, now);
+                } else {
+                    apply_grant(&mut current, None, false, now);
+                }
+            }
+            let updates = if byte < 4 { vec![action_hash(byte + 1)] } else { Vec::new() };
+            chain.insert(action_hash(byte), (byte as i64, updates, current.clone()));
+        }
+
+        let history = permission_versions(action_hash(1), |hash| {
+            Ok(chain.get(hash).map(|(micros, updates, permission)| PermissionVersion {
+                timestamp: Timestamp::from_micros(*micros),
+                updates: updates.clone(),
+                permission: Some(permission.clone()),
+            }))
+        }).unwrap();
+
+        assert_eq!(history.len(), 4);
+        let granted: Vec<bool> = history.iter().map(|version| version.granted).collect();
+        assert_eq!(granted, vec![true, false, true, false]);
+    }
+
+    #[test]
+    fn usage_history_lists_each_recorded_use_once() {
+        // Usage at 200 and 300; the revoke at 400 carries the 300 use forward unchanged
+        let mut current = permission("billing", true, None);
+        let mut history = vec![current.clone()];
+        for now in [200, 300] {
+            apply_usage(&mut current, now);
+            history.push(current.clone());
+        }
+        apply_revoke(&mut current, 400);
+        history.push(current);
+
+        assert_eq!(usage_timeline(history), vec![200, 300]);
+        assert!(usage_timeline(vec![permission("billing", true, None)]).is_empty());
+    }
+
+    #[test]
+    fn normalize_service_name_trims_and_lowercases() {
+        assert_eq!(normalize_service_name("  Billing "), "billing");
+        assert_eq!(normalize_service_name("SECURITY_alerts"), "security_alerts");
+        assert_eq!(normalize_service_name(""), "");
+    }
+
+    #[test]
+    fn valid_profile_input_has_no_violations() {
+        assert!(validate_user_profile_input(&profile(100, 100), false).is_empty());
+    }
+
+    #[test]
+    fn profile_input_rejects_bad_base64_and_timestamps() {
+        let mut bad = profile(100, 50);
+        bad.salt = "not base64!".into();
+        let violations = validate_user_profile_input(&bad, false);
+        assert!(violations.iter().any(|v| v.contains("salt")));
+        assert!(violations.iter().any(|v| v.contains("updated_at")));
+    }
+
+    #[test]
+    fn profile_input_reports_every_violation_at_once() {
+        let mut bad = profile(100, 50);
+        bad.encrypted_email = String::new();
+        bad.display_name = "x".repeat(MAX_DISPLAY_NAME_LEN + 1);
+        let violations = validate_user_profile_input(&bad, false);
+        assert_eq!(violations, vec![
+            "encrypted_email is required".to_string(),
+            format!("display_name exceeds {} characters", MAX_DISPLAY_NAME_LEN),
+            "updated_at cannot be earlier than created_at".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn username_equal_to_display_name_warns_or_rejects() {
+        let mut same = profile(100, 100);
+        same.username = Some(same.display_name.clone());
+        assert!(validate_user_profile_input(&same, false).is_empty());
+        assert_eq!(
+            validate_user_profile_input(&same, true),
+            vec!["username must differ from display_name".to_string()]
+        );
+
+        let mut distinct = profile(100, 100);
+        distinct.username = Some("ada_l".into());
+        assert!(validate_user_profile_input(&distinct, true).is_empty());
+    }
+
+    #[test]
+    fn set_username_to_display_name_warns_or_rejects() {
+        let renamed = with_username(profile(100, 100), Some("Ada".into()), 300);
+        assert_eq!(renamed.updated_at, 300);
+        assert!(validate_user_profile_input(&renamed, false).is_empty());
+        assert_eq!(
+            validate_user_profile_input(&renamed, true),
+            vec!["username must differ from display_name".to_string()]
+        );
+
+        let cleared = with_username(renamed, None, 400);
+        assert!(validate_user_profile_input(&cleared, true).is_empty());
+    }
+
+    #[test]
+    fn crypto_field_accepts_the_expected_length_as_hex_or_base64() {
+        let hex_nonce = "00112233445566778899aabb";
+        let base64_nonce = STANDARD.encode([7u8; 12]);
+        for value in [hex_nonce, base64_nonce.as_str()] {
+            let check = check_crypto_field(value, NONCE_LEN);
+            assert!(check.valid, "{} should be a valid nonce", value);
+            assert_eq!(check.decoded_len, Some(NONCE_LEN));
+        }
+        assert!(check_crypto_field(&"ab".repeat(SALT_LEN as usize), SALT_LEN).valid);
+    }
+
+    #[test]
+    fn crypto_field_rejects_wrong_lengths_and_garbage() {
+        // 8-byte nonces (the v1.4 bug) are rejected in either encoding
+        let short_hex = check_crypto_field("0011223344556677", NONCE_LEN);
+        assert!(!short_hex.valid);
+        assert_eq!(short_hex.decoded_len, Some(8));
+        assert!(!check_crypto_field(&STANDARD.encode([7u8; 8]), NONCE_LEN).valid);
+
+        let garbage = check_crypto_field("not base64!", AUTH_TAG_LEN);
+        assert!(!garbage.valid);
+        assert_eq!(garbage.decoded_len, None);
+    }
+
+    #[test]
+    fn supported_dna_versions_are_any_1x_up_to_current() {
+        assert!(is_supported_dna_version("1.0"));
+        assert!(is_supported_dna_version(DNA_VERSION));
+        assert!(!is_supported_dna_version("1.999"));
+        assert!(!is_supported_dna_version("2.0"));
+        assert!(!is_supported_dna_version("1"));
+        assert!(!is_supported_dna_version("garbage"));
+    }
+
+    fn compress(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// The bundle layout v1.0 exported: no email permissions, activity, analytics, TOTP, picture
+    /// or metadata beyond the timestamp and version, and no is_current/username fields
+    #[derive(Serialize)]
+    struct V1_0Profile {
+        encrypted_email: String,
+        nonce: String,
+        salt: String,
+        tag: String,
+        display_name: String,
+        created_at: i64,
+        updated_at: i64,
+    }
+
+    #[derive(Serialize)]
+    struct V1_0Session {
+        user_agent: String,
+        ip_address: String,
+        device_info: String,
+        conductor_id: String,
+        created_at: i64,
+        last_active: i64,
+    }
+
+    #[derive(Serialize)]
+    struct V1_0Bundle {
+        user_profile: Option<V1_0Profile>,
+        recovery_phrase: Option<RecoveryPhrase>,
+        sessions: Vec<V1_0Session>,
+        export_timestamp: i64,
+        dna_version: String,
+    }
+
+    #[test]
+    fn v1_0_bundle_deserializes_with_later_collections_defaulted() {
+        let v1_0 = V1_0Bundle {
+            user_profile: Some(V1_0Profile {
+                encrypted_email: "ZW1haWw=".into(),
+                nonce: "bm9uY2U=".into(),
+                salt: "c2FsdA==".into(),
+                tag: "dGFn".into(),
+                display_name: "Ada".into(),
+                created_at: 100,
+                updated_at: 100,
+            }),
+            recovery_phrase: None,
+            sessions: vec![V1_0Session {
+                user_agent: "Mozilla/5.0".into(),
+                ip_address: "203.0.113.7".into(),
+                device_info: "laptop".into(),
+                conductor_id: "edge-1".into(),
+                created_at: 100,
+                last_active: 150,
+            }],
+            export_timestamp: 200,
+            dna_version: "1.0".into(),
+        };
+        let bytes = holochain_serialized_bytes::encode(&v1_0).unwrap();
+
+        let data: ExportedData = holochain_serialized_bytes::decode(&bytes).unwrap();
+
+        assert_eq!(data.user_profile, Some(profile(100, 100)));
+        assert_eq!(data.sessions.len(), 1);
+        assert!(!data.sessions[0].is_current);
+        assert!(data.email_permissions.is_empty());
+        assert!(data.login_activities.is_empty());
+        assert!(data.dashboard_activities.is_empty());
+        assert!(data.oauth_activities.is_empty());
+        assert!(data.analytics_ids.is_empty());
+        assert_eq!(data.privacy_settings, None);
+        assert_eq!(data.totp_config, None);
+        assert_eq!(data.profile_picture, None);
+        assert!(data.sections.is_empty());
+        assert_eq!(data.export_format_version, 1);
+        assert_eq!(data.exported_by, None);
+        assert_eq!(data.selected_types, None);
+        assert!(is_supported_dna_version(&data.dna_version));
+    }
+
+    #[test]
+    fn compressed_bundle_round_trips() {
+        let mut data = bundle();
+        data.user_profile = Some(profile(100, 200));
+        data.login_activities = (1..=500).map(login).collect();
+        data.oauth_activities = vec![OAuthActivity {
+            timestamp: 300,
+            app_id: "app-1".into(),
+            app_name: "App".into(),
+            event_type: "consent_granted".into(),
+            created_at: 300,
+            seq: Some(1),
+            scopes: vec!["email".into()],
+        }];
+        data.exported_by = Some(agent_key(1));
+
+        let compressed = compress_bundle(&data).unwrap();
+        let decoded = decode_compressed_bundle(&compressed, MAX_DECOMPRESSED_BYTES).unwrap();
+
+        assert_eq!(decoded.user_profile, data.user_profile);
+        assert_eq!(decoded.login_activities, data.login_activities);
+        assert_eq!(decoded.oauth_activities, data.oauth_activities);
+        assert_eq!(decoded.exported_by, data.exported_by);
+        assert_eq!(decoded.export_timestamp, data.export_timestamp);
+        assert_eq!(decoded.dna_version, data.dna_version);
+        assert_eq!(decoded.export_format_version, data.export_format_version);
+    }
+
+    #[test]
+    fn compressed_garbage_is_not_a_bundle() {
+        assert!(decode_compressed_bundle(&compress(b"not a bundle"), MAX_DECOMPRESSED_BYTES).is_err());
+    }
+
+    #[test]
+    fn decompress_bundle_accepts_output_up_to_the_limit() {
+        let payload = vec![7u8; 1000];
+        assert_eq!(decompress_bundle(&compress(&payload), 1000).unwrap(), payload);
+    }
+
+    #[test]
+    fn decompress_bundle_rejects_output_over_the_limit() {
+        let bomb = compress(&vec![0u8; 1_000_000]);
+        assert!(bomb.len() < 10_000);
+        assert!(decompress_bundle(&bomb, 999_999).is_err());
+    }
+
+    fn activity_link(byte: u8, tag: LinkTag) -> Link {
+        Link {
+            author: agent_key(1),
+            base: agent_key(1).into(),
+            target: action_hash(byte).into(),
+            timestamp: Timestamp::from_micros(0),
+            zome_index: 0.into(),
+            link_type: 0.into(),
+            tag,
+            create_link_hash: action_hash(byte.wrapping_add(100)),
+        }
+    }
+
+    fn oauth(app_id: &str, created_at: i64) -> OAuthActivity {
+        OAuthActivity {
+            timestamp: created_at,
+            app_id: app_id.into(),
+            app_name: app_id.into(),
+            event_type: "login".into(),
+            created_at,
+            seq: None,
+            scopes: vec![],
+        }
+    }
+
+    /// Entries and the links their create_* function writes, as the DHT holds them
+    struct ActivityState {
+        logins: Vec<(Link, LoginActivity)>,
+        oauth: Vec<(Link, OAuthActivity)>,
+    }
+
+    impl ActivityState {
+        fn new(logins: Vec<LoginActivity>, oauth: Vec<OAuthActivity>) -> Self {
+            let logins = logins
+                .into_iter()
+                .enumerate()
+                .map(|(index, activity)| {
+                    let key = (index % 2 == 0).then_some("retry-1");
+                    (activity_link(index as u8, login_activity_tag(&activity, key)), activity)
+                })
+                .collect();
+            let oauth = oauth
+                .into_iter()
+                .enumerate()
+                .map(|(index, activity)| (activity_link(50 + index as u8, oauth_activity_tag(&activity, Some("retry-2"))), activity))
+                .collect();
+            ActivityState { logins, oauth }
+        }
+
+        /// What delete_old_login_activity / delete_old_oauth_activity leave behind: entry and link go together
+        fn delete_created_before(&mut self, cutoff: i64) {
+            self.logins.retain(|(_, activity)| activity.created_at >= cutoff);
+            self.oauth.retain(|(_, activity)| activity.created_at >= cutoff);
+        }
+
+        fn slow(&self, thirty_days_ago: i64) -> ActivitySummary {
+            let logins: Vec<LoginActivity> = self.logins.iter().map(|(_, activity)| activity.clone()).collect();
+            let oauth: Vec<OAuthActivity> = self.oauth.iter().map(|(_, activity)| activity.clone()).collect();
+            summary_from_records(&logins, &oauth, 3, thirty_days_ago)
+        }
+
+        fn fast(&self, thirty_days_ago: i64) -> Option<ActivitySummary> {
+            let login_links: Vec<Link> = self.logins.iter().map(|(link, _)| link.clone()).collect();
+            let oauth_links: Vec<Link> = self.oauth.iter().map(|(link, _)| link.clone()).collect();
+            summary_from_links(&login_links, &oauth_links, 3, thirty_days_ago)
+        }
+    }
+
+    #[test]
+    fn app_activity_page_only_fetches_that_apps_links() {
+        // Oldest first, as get_links returns them; the third link predates tagging
+        let mut state = ActivityState::new(vec![], vec![
+            oauth("app-1", 100),
+            oauth("app-2", 200),
+            oauth("app-1", 300),
+            oauth("app-2", 400),
+            oauth("app-1", 500),
+        ]);
+        state.oauth[2].0.tag = LinkTag::new(vec![]);
+        let links: Vec<Link> = state.oauth.iter().map(|(link, _)| link.clone()).collect();
+
+        let mut fetched = Vec::new();
+        let page = page_of_app_activity(&links, "app-1", 2, 0, |link| {
+            fetched.push(link.target.clone());
+            Ok(state.oauth.iter().find(|(stored, _)| stored.target == link.target).map(|(_, activity)| activity.clone()))
+        })
+        .unwrap();
+
+        let timestamps: Vec<i64> = page.iter().map(|activity| activity.timestamp).collect();
+        assert_eq!(timestamps, vec![500, 300]);
+        // The newest app-2 link was skipped by tag, and the page filled before the oldest link
+        assert_eq!(fetched, vec![links[4].target.clone(), links[2].target.clone()]);
+    }
+
+    #[test]
+    fn deleting_one_apps_activity_leaves_the_other_app_alone() {
+        // The last link predates tagging, so it is fetched to check its app_id
+        let mut state = ActivityState::new(vec![], vec![
+            oauth("app-1", 100),
+            oauth("app-2", 200),
+            oauth("app-1", 300),
+            oauth("app-2", 400),
+        ]);
+        state.oauth[3].0.tag = LinkTag::new(vec![]);
+        let links: Vec<Link> = state.oauth.iter().map(|(link, _)| link.clone()).collect();
+
+        let lookup = |link: &Link| -> ExternResult<Option<OAuthActivity>> {
+            Ok(state.oauth.iter().find(|(stored, _)| stored.target == link.target).map(|(_, activity)| activity.clone()))
+        };
+        let deletions = app_activity_deletions(&links, "app-1", lookup).unwrap();
+        let expected: Vec<(ActionHash, ActionHash)> = [&links[0], &links[2]]
+            .iter()
+            .map(|link| (ActionHash::try_from(link.target.clone()).unwrap(), link.create_link_hash.clone()))
+            .collect();
+        assert_eq!(deletions, expected);
+
+        state.oauth.retain(|(link, _)| !deletions.iter().any(|(_, link_hash)| *link_hash == link.create_link_hash));
+        let remaining: Vec<&str> = state.oauth.iter().map(|(_, activity)| activity.app_id.as_str()).collect();
+        assert_eq!(remaining, vec!["app-2", "app-2"]);
+    }
+
+    #[test]
+    fn activity_availability_flips_as_links_are_added() {
+        let mut links: Vec<LinkTypes> = Vec::new();
+        let availability = |links: &[LinkTypes]| {
+            activity_availability(|link_type| Ok(links.iter().filter(|stored| **stored == link_type).count())).unwrap()
+        };
+        assert_eq!(availability(&links), ActivityAvailability { login: false, dashboard: false, oauth: false });
+
+        links.push(LinkTypes::AgentToLoginActivity);
+        links.push(LinkTypes::AgentToSessions);
+        assert_eq!(availability(&links), ActivityAvailability { login: true, dashboard: false, oauth: false });
+
+        links.push(LinkTypes::AgentToOAuthActivity);
+        links.push(LinkTypes::AgentToDashboardActivity);
+        assert_eq!(availability(&links), ActivityAvailability { login: true, dashboard: true, oauth: true });
+    }
+
+    #[test]
+    fn active_apps_only_count_events_inside_the_range() {
+        let activities = vec![
+            oauth("calendar", 50),     // before the range
+            oauth("mail", 100),
+            oauth("chat", 150),
+            oauth("mail", 199),
+            oauth("photos", 200),      // end is exclusive
+        ];
+        assert_eq!(apps_active_between(activities, 100, 200), vec!["chat".to_string(), "mail".to_string()]);
+        assert!(apps_active_between(vec![oauth("mail", 10)], 100, 200).is_empty());
+    }
+
+    #[test]
+    fn app_activity_page_applies_the_offset_to_matches_only() {
+        let state = ActivityState::new(vec![], vec![
+            oauth("app-1", 100),
+            oauth("app-2", 200),
+            oauth("app-1", 300),
+            oauth("app-1", 500),
+        ]);
+        let links: Vec<Link> = state.oauth.iter().map(|(link, _)| link.clone()).collect();
+        let fetch = |link: &Link| {
+            Ok(state.oauth.iter().find(|(stored, _)| stored.target == link.target).map(|(_, activity)| activity.clone()))
+        };
+
+        let page = page_of_app_activity(&links, "app-1", 10, 1, fetch).unwrap();
+        let timestamps: Vec<i64> = page.iter().map(|activity| activity.timestamp).collect();
+        assert_eq!(timestamps, vec![300, 100]);
+        assert!(page_of_app_activity(&links, "app-1", 10, 3, fetch).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fast_summary_from_links_matches_slow_summary_from_records() {
+        let state = ActivityState::new(
+            vec![login(500), login(1_000), login(2_500), login(2_500)],
+            vec![oauth("app-1", 100), oauth("app-2", 2_000), oauth("app-1", 2_100)],
+        );
+        let expected = ActivitySummary {
+            total_logins: 4,
+            logins_last_30_days: 3,
+            unique_apps_used: 2,
+            dashboard_visits: 3,
+            last_login: Some(2_500),
+        };
+        assert_eq!(state.slow(1_000), expected);
+        assert_eq!(state.fast(1_000), Some(expected));
+    }
+
+    #[test]
+    fn summaries_still_agree_after_retention_deletes_entries_and_links() {
+        let mut state = ActivityState::new(
+            vec![login(500), login(1_000), login(2_500)],
+            vec![oauth("app-1", 100), oauth("app-2", 2_000)],
+        );
+        state.delete_created_before(1_000);
+        let expected = ActivitySummary {
+            total_logins: 2,
+            logins_last_30_days: 2,
+            unique_apps_used: 1,
+            dashboard_visits: 3,
+            last_login: Some(2_500),
+        };
+        assert_eq!(state.slow(1_000), expected);
+        assert_eq!(state.fast(1_000), Some(expected));
+    }
+
+    #[test]
+    fn fast_summary_falls_back_on_untagged_links() {
+        let mut state = ActivityState::new(vec![login(500)], vec![oauth("app-1", 100)]);
+        state.oauth[0].0.tag = LinkTag::new(vec![]);
+        assert_eq!(state.fast(0), None);
+
+        let mut state = ActivityState::new(vec![login(500)], vec![]);
+        state.logins[0].0.tag = LinkTag::new(vec![]);
+        assert_eq!(state.fast(0), None);
+    }
+
+    #[test]
+    fn delete_sessions_finds_the_link_of_every_deleted_session() {
+        let links = vec![
+            (AnyLinkableHash::from(action_hash(1)), action_hash(101)),
+            (AnyLinkableHash::from(action_hash(2)), action_hash(102)),
+            (AnyLinkableHash::from(action_hash(3)), action_hash(103)),
+            (AnyLinkableHash::from(action_hash(3)), action_hash(104)),
+        ];
+        assert_eq!(
+            link_hashes_targeting(&links, &[action_hash(1), action_hash(3), action_hash(9)]),
+            vec![action_hash(101), action_hash(103), action_hash(104)]
+        );
+        assert!(link_hashes_targeting(&links, &[]).is_empty());
+    }
+
+    /// Record details for `action_hash(byte)` deleted by the given actions
+    fn session_record(byte: u8, deletes: Vec<SignedActionHashed>) -> Details {
+        Details::Record(RecordDetails {
+            record: Record::new(signed_create(byte, byte as i64), None),
+            validation_status: ValidationStatus::Valid,
+            deletes,
+            updates: vec![],
+        })
+    }
+
+    fn session_link(target: u8) -> Link {
+        Link { create_link_hash: action_hash(target + 200), ..profile_link(target) }
+    }
+
+    #[test]
+    fn logout_everywhere_deletes_live_sessions_and_every_link() {
+        // Session 2 was already deleted but its link remains
+        let store = |hash: &ActionHash| -> ExternResult<Option<Details>> {
+            Ok(match hash.get_raw_36()[0] {
+                2 => Some(session_record(2, vec![signed_delete(9, 2)])),
+                byte @ (1 | 3) => Some(session_record(byte, vec![])),
+                _ => None,
+            })
+        };
+        let links = vec![session_link(1), session_link(2), session_link(3)];
+        let (sessions, link_hashes) = sessions_to_clear(links, store).unwrap();
+        assert_eq!(sessions, vec![action_hash(1), action_hash(3)]);
+        // get_my_sessions reads through these links, so with all of them gone it is empty
+        assert_eq!(link_hashes, vec![action_hash(201), action_hash(202), action_hash(203)]);
+    }
+
+    fn session_head(byte: u8, last_active: i64, is_current: bool) -> SessionHead {
+        SessionHead {
+            original_hash: action_hash(byte),
+            head_hash: action_hash(byte + 100),
+            link_hash: action_hash(byte + 200),
+            session: Session {
+                user_agent: "Firefox".into(),
+                ip_address: "203.0.113.1".into(),
+                device_info: format!("device-{}", byte),
+                conductor_id: "edge-1".into(),
+                created_at: 1,
+                last_active,
+                is_current,
+            },
+        }
+    }
+
+    fn head_on(byte: u8, conductor_id: &str) -> SessionHead {
+        let mut head = session_head(byte, 10, false);
+        head.session.conductor_id = conductor_id.into();
+        head
+    }
+
+    /// Record at `action_hash(byte)` holding the session from session_head
+    fn session_entry_record(byte: u8, last_active: i64) -> Record {
+        let bytes = SerializedBytes::try_from(session_head(byte, last_active, false).session).unwrap();
+        record_with(byte, app_entry(bytes))
+    }
+
+    #[test]
+    fn active_sessions_are_ordered_most_recent_first() {
+        let records = vec![
+            session_entry_record(1, 200),
+            session_entry_record(2, 900),
+            record_with(3, None),
+            session_entry_record(4, 500),
+        ];
+        let order: Vec<ActionHash> = most_recently_active_first(records)
+            .iter()
+            .map(|record| record.action_address().clone())
+            .collect();
+        assert_eq!(order, vec![action_hash(2), action_hash(4), action_hash(1)]);
+    }
+
+    #[test]
+    fn sessions_by_conductor_only_match_that_conductor() {
+        let heads = vec![head_on(1, "edge-1"), head_on(2, "edge-2"), head_on(3, "edge-1")];
+        let records: Vec<Record> = heads
+            .iter()
+            .map(|head| {
+                let bytes = SerializedBytes::try_from(head.session.clone()).unwrap();
+                record_with(head.original_hash.get_raw_36()[0], app_entry(bytes))
+            })
+            .collect();
+
+        let found: Vec<ActionHash> = session_records_on_conductor(records.clone(), "edge-1")
+            .iter()
+            .map(|record| record.action_address().clone())
+            .collect();
+        assert_eq!(found, vec![action_hash(1), action_hash(3)]);
+        assert_eq!(session_records_on_conductor(records, "edge-2").len(), 1);
+
+        let deleted: Vec<ActionHash> = session_heads_on_conductor(heads, "edge-2")
+            .into_iter()
+            .map(|head| head.original_hash)
+            .collect();
+        assert_eq!(deleted, vec![action_hash(2)]);
+    }
+
+    #[test]
+    fn legacy_sessions_convert_to_equivalent_login_activity() {
+        let mut session = session_head(1, 5_000, false).session;
+        session.ip_address = String::new();  // Not recorded for this session
+        let activity = legacy_login_activity(&action_hash(1), &session);
+        assert_eq!(activity.timestamp, session.created_at);
+        assert_eq!(activity.created_at, session.created_at);
+        assert_eq!(activity.login_method, LEGACY_SESSION_LOGIN_METHOD);
+        assert_eq!(activity.session_id, action_hash(1).to_string());
+        assert_eq!(activity.user_agent.as_deref(), Some(session.user_agent.as_str()));
+        assert_eq!(activity.ip_address, None);
+    }
+
+    #[test]
+    fn migrated_sessions_are_not_converted_again() {
+        let session = session_head(1, 5_000, false).session;
+        let mut existing = vec![legacy_login_activity(&action_hash(1), &session)];
+        assert!(is_migrated_session(&action_hash(1), &existing));
+        assert!(!is_migrated_session(&action_hash(2), &existing));
+
+        // A real login that happens to reuse the id doesn't count as a migration
+        existing[0].login_method = "password".into();
+        assert!(!is_migrated_session(&action_hash(1), &existing));
+    }
+
+    #[test]
+    fn set_current_session_leaves_exactly_one_current() {
+        let mut sessions = vec![session_head(1, 10, true), session_head(2, 20, false), session_head(3, 30, false)];
+        // The head hash from get_my_sessions names the session as well as the original create
+        let changed = mark_current_session(&mut sessions, &action_hash(102)).unwrap();
+        assert_eq!(changed, vec![0, 1]);
+        let current: Vec<&ActionHash> = sessions.iter()
+            .filter(|head| head.session.is_current)
+            .map(|head| &head.original_hash)
+            .collect();
+        assert_eq!(current, vec![&action_hash(2)]);
+
+        assert!(mark_current_session(&mut sessions, &action_hash(2)).unwrap().is_empty());
+        assert_eq!(sessions.iter().filter(|head| head.session.is_current).count(), 1);
+    }
+
+    #[test]
+    fn eviction_over_the_cap_drops_the_least_recently_active() {
+        // Ordered by head last_active (after touch_session), not by creation order
+        let sessions = vec![session_head(1, 40, false), session_head(2, 10, false), session_head(3, 30, false), session_head(4, 20, false)];
+        let evicted: Vec<ActionHash> = sessions_to_evict(sessions, 2)
+            .into_iter()
+            .map(|head| head.original_hash)
+            .collect();
+        assert_eq!(evicted, vec![action_hash(2), action_hash(4)]);
+    }
+
+    #[test]
+    fn eviction_at_or_under_the_cap_keeps_everything() {
+        assert!(sessions_to_evict(vec![session_head(1, 10, false), session_head(2, 20, false)], 2).is_empty());
+        assert!(sessions_to_evict(Vec::new(), 0).is_empty());
+    }
+
+    #[test]
+    fn set_current_session_rejects_an_unknown_hash() {
+        let mut sessions = vec![session_head(1, 10, true)];
+        assert!(mark_current_session(&mut sessions, &action_hash(9)).is_err());
+        assert!(sessions[0].session.is_current);
+    }
+
+    #[test]
+    fn resolve_original_walks_head_hashes_back_to_the_create() {
+        // 3 updates 2, which updates the create 1
+        let updated_from = |hash: &ActionHash| -> ExternResult<Option<ActionHash>> {
+            Ok(match hash.get_raw_36()[0] {
+                3 => Some(action_hash(2)),
+                2 => Some(action_hash(1)),
+                _ => None,
+            })
+        };
+        assert_eq!(resolve_original(action_hash(3), updated_from).unwrap(), action_hash(1));
+        assert_eq!(resolve_original(action_hash(2), updated_from).unwrap(), action_hash(1));
+        assert_eq!(resolve_original(action_hash(1), updated_from).unwrap(), action_hash(1));
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct LoginActivityWithKey {
+        timestamp: i64,
+        login_method: String,
+        session_id: String,
+        created_at: i64,
+        idempotency_key: Option<String>,
+    }
+
+    #[test]
+    fn store_input_reads_the_key_beside_the_entry_fields() {
+        let wire = LoginActivityWithKey {
+            timestamp: 1_700_000_000_000_000,
+            login_method: "password".into(),
+            session_id: "abc".into(),
+            created_at: 1_700_000_000_000_000,
+            idempotency_key: Some("retry-1".into()),
+        };
+        let bytes = holochain_serialized_bytes::encode(&wire).unwrap();
+        let input: StoreLoginActivityInput = holochain_serialized_bytes::decode(&bytes).unwrap();
+        assert_eq!(input.idempotency_key.as_deref(), Some("retry-1"));
+        assert_eq!(input.activity.timestamp, wire.timestamp);
+        assert_eq!(input.activity.session_id, "abc");
+        assert_eq!(input.activity.ip_address, None);
+    }
+
+    #[test]
+    fn format_uuid_v4_sets_version_and_variant() {
+        assert_eq!(format_uuid_v4([0u8; 16]), "00000000-0000-4000-8000-000000000000");
+        assert_eq!(format_uuid_v4([0xffu8; 16]), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+    }
+
+    #[test]
+    fn rotation_replaces_the_id_for_the_same_app() {
+        let current = AppAnalyticsId {
+            app_id: "app-1".into(),
+            analytics_id: format_uuid_v4([1u8; 16]),
+            created_at: 100,
+        };
+        let rotated = rotated_analytics_id(&current, format_uuid_v4([2u8; 16]), 200);
+        assert_eq!(rotated.app_id, current.app_id);
+        assert_ne!(rotated.analytics_id, current.analytics_id);
+        assert_eq!(rotated.created_at, 200);
+    }
+
+    fn analytics_id(app_id: &str, created_at: i64) -> AppAnalyticsId {
+        AppAnalyticsId { app_id: app_id.into(), analytics_id: format_uuid_v4([created_at as u8; 16]), created_at }
+    }
+
+    #[test]
+    fn dedupe_keeps_the_oldest_analytics_id_per_app() {
+        let linked = vec![
+            (analytics_id("app-1", 300), action_hash(1), action_hash(101)),
+            (analytics_id("app-1", 100), action_hash(2), action_hash(102)),
+            (analytics_id("app-2", 200), action_hash(3), action_hash(103)),
+            (analytics_id("app-1", 200), action_hash(4), action_hash(104)),
+            // A second link to app-1's kept entry: only the link goes
+            (analytics_id("app-1", 100), action_hash(2), action_hash(105)),
+        ];
+        let mut removed = duplicate_analytics_ids(linked);
+        removed.sort_by(|(_, a), (_, b)| a.cmp(b));
+        assert_eq!(removed, vec![
+            (Some(action_hash(1)), action_hash(101)),
+            (Some(action_hash(4)), action_hash(104)),
+            (None, action_hash(105)),
+        ]);
+    }
+
+    #[test]
+    fn dedupe_without_duplicates_removes_nothing() {
+        let linked = vec![
+            (analytics_id("app-1", 100), action_hash(1), action_hash(101)),
+            (analytics_id("app-2", 100), action_hash(2), action_hash(102)),
+        ];
+        assert!(duplicate_analytics_ids(linked).is_empty());
+    }
+
+    fn bundle() -> ExportedData {
+        ExportedData {
+            user_profile: None,
+            recovery_phrase: None,
+            sessions: Vec::new(),
+            email_permissions: Vec::new(),
+            login_activities: Vec::new(),
+            dashboard_activities: Vec::new(),
+            oauth_activities: Vec::new(),
+            privacy_settings: None,
+            analytics_ids: Vec::new(),
+            totp_config: None,
+            profile_picture: None,
+            export_timestamp: 1_000,
+            dna_version: DNA_VERSION.to_string(),
+            sections: Vec::new(),
+            export_format_version: EXPORT_FORMAT_VERSION,
+            exported_by: None,
+            selected_types: None,
+        }
+    }
+
+    fn login(timestamp: i64) -> LoginActivity {
+        LoginActivity {
+            timestamp,
+            login_method: "password".into(),
+            ip_address: None,
+            user_agent: None,
+            session_id: format!("session-{}", timestamp),
+            created_at: timestamp,
+            seq: None,
+            region: None,
+        }
+    }
+
+    #[test]
+    fn first_login_is_the_oldest_not_the_first_linked() {
+        let first = earliest_login(vec![login(300), login(100), login(200)]).unwrap();
+        assert_eq!(first.timestamp, 100);
+        assert_eq!(first.session_id, "session-100");
+        assert!(earliest_login(vec![]).is_none());
+    }
+
+    fn login_from(ip: Option<&str>, timestamp: i64) -> LoginActivity {
+        LoginActivity { ip_address: ip.map(str::to_string), ..login(timestamp) }
+    }
+
+    #[test]
+    fn repeated_ips_are_listed_once_with_their_newest_login() {
+        let activities = vec![
+            login_from(Some("198.51.100.7"), 100),
+            login_from(Some("203.0.113.1"), 200),
+            login_from(Some("198.51.100.7"), 300),
+            login_from(None, 400),
+        ];
+        assert_eq!(
+            recent_login_ips(activities, true, 10),
+            vec![("198.51.100.7".to_string(), 300), ("203.0.113.1".to_string(), 200)]
+        );
+    }
+
+    #[test]
+    fn recent_ips_stop_at_the_limit_of_distinct_ips() {
+        let activities = vec![
+            login_from(Some("192.0.2.1"), 100),
+            login_from(Some("192.0.2.2"), 200),
+            login_from(Some("192.0.2.2"), 250),
+            login_from(Some("192.0.2.3"), 300),
+        ];
+        let ips: Vec<String> = recent_login_ips(activities, true, 2).into_iter().map(|(ip, _)| ip).collect();
+        assert_eq!(ips, vec!["192.0.2.3".to_string(), "192.0.2.2".to_string()]);
+    }
+
+    #[test]
+    fn recent_ips_are_hidden_while_ip_tracking_is_off() {
+        let activities = vec![login_from(Some("192.0.2.1"), 100), login_from(Some("192.0.2.2"), 200)];
+        assert_eq!(recent_login_ips(activities.clone(), true, 10).len(), 2);
+        assert!(recent_login_ips(activities, false, 10).is_empty());
+    }
+
+    fn login_with_session(session_id: &str, timestamp: i64) -> LoginActivity {
+        LoginActivity { session_id: session_id.into(), ..login(timestamp) }
+    }
+
+    #[test]
+    fn session_ids_on_more_than_one_login_are_reported_once_sorted() {
+        let activities = vec![
+            login_with_session("zeta", 1),
+            login_with_session("alpha", 2),
+            login_with_session("unique", 3),
+            login_with_session("zeta", 4),
+            login_with_session("alpha", 5),
+            login_with_session("zeta", 6),
+        ];
+        assert_eq!(duplicate_session_ids(activities), vec!["alpha".to_string(), "zeta".to_string()]);
+    }
+
+    #[test]
+    fn distinct_session_ids_have_no_duplicates() {
+        assert!(duplicate_session_ids(vec![login(1), login(2), login(3)]).is_empty());
+        assert!(duplicate_session_ids(vec![]).is_empty());
+    }
+
+    #[test]
+    fn double_submitted_login_is_stored_once() {
+        let mut stored: Vec<LoginActivity> = Vec::new();
+        let mut store = |activity: LoginActivity| {
+            if !stored.last().is_some_and(|latest| is_near_duplicate_login(latest, &activity)) {
+                stored.push(activity);
+            }
+        };
+        let first = login(1_000_000);
+        store(first.clone());
+        store(LoginActivity { timestamp: first.timestamp + 1_000, ..first.clone() });
+        assert_eq!(stored.len(), 1);
+    }
+
+    #[test]
+    fn logins_outside_the_window_or_for_another_session_are_not_duplicates() {
+        let first = login(1_000_000);
+        let later = LoginActivity { timestamp: first.timestamp + LOGIN_DEDUPE_WINDOW_MICROS, ..first.clone() };
+        let other_session = LoginActivity { session_id: "session-other".into(), ..first.clone() };
+        assert!(!is_near_duplicate_login(&first, &later));
+        assert!(!is_near_duplicate_login(&first, &other_session));
+        assert!(is_near_duplicate_login(&later, &LoginActivity { timestamp: later.timestamp - 1, ..first }));
+    }
+
+    #[test]
+    fn activity_import_over_the_per_type_limit_is_rejected() {
+        let mut activities = ActivityExport {
+            login_activities: vec![login(1); MAX_IMPORT_ITEMS_PER_TYPE as usize],
+            dashboard_activities: vec![],
+            oauth_activities: vec![],
+            privacy_settings: None,
+        };
+        assert!(ensure_import_within_limits(&ImportPlan::from(&activities)).is_ok());
+
+        activities.login_activities.push(login(2));
+        let oversized = oversized_import_types(&ImportPlan::from(&activities));
+        assert_eq!(oversized, vec![format!("login_activities has {} items", MAX_IMPORT_ITEMS_PER_TYPE + 1)]);
+        assert!(ensure_import_within_limits(&ImportPlan::from(&activities)).is_err());
+    }
+
+    #[test]
+    fn activity_import_leaves_profile_and_credentials_alone() {
+        let activities = ActivityExport {
+            login_activities: vec![login(300), login(100)],
+            dashboard_activities: vec![],
+            oauth_activities: vec![oauth("app", 200)],
+            privacy_settings: Some(default_privacy_settings(1)),
+        };
+        let items = activity_import_items(activities);
+        assert_eq!(items.len(), 4);
+        assert!(items.iter().all(|item| matches!(
+            item,
+            ImportItem::LoginActivity(_)
+                | ImportItem::DashboardActivity(_)
+                | ImportItem::OAuthActivity(_)
+                | ImportItem::PrivacySettings(Some(_))
+        )));
+
+        let logins: Vec<i64> = items
+            .iter()
+            .filter_map(|item| match item {
+                ImportItem::LoginActivity(activity) => Some(activity.timestamp),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(logins, vec![100, 300]);
+    }
+
+    #[test]
+    fn page_size_is_clamped_at_the_boundary() {
+        let unused = || -> ExternResult<u32> { panic!("the setting is only read without a limit") };
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE - 1), unused).unwrap(), 499);
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE), unused).unwrap(), 500);
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE + 1), unused).unwrap(), 500);
+        assert_eq!(page_size(Some(u32::MAX), unused).unwrap(), 500);
+    }
+
+    #[test]
+    fn page_size_falls_back_to_the_setting_which_is_clamped_too() {
+        assert_eq!(page_size(None, || Ok(25)).unwrap(), 25);
+        assert_eq!(page_size(None, || Ok(10_000)).unwrap(), 500);
+        assert!(page_size(None, || Err(wasm_error!("settings unreadable"))).is_err());
+    }
+
+    #[test]
+    fn offset_is_accepted_up_to_the_maximum_and_rejected_beyond() {
+        assert_eq!(resolve_offset(None).unwrap(), 0);
+        assert_eq!(resolve_offset(Some(MAX_PAGE_OFFSET)).unwrap(), MAX_PAGE_OFFSET as usize);
+        let error = resolve_offset(Some(MAX_PAGE_OFFSET + 1)).unwrap_err();
+        assert!(format!("{:?}", error).contains("InvalidPagination"));
+    }
+
+    #[test]
+    fn default_settings_score_only_the_partial_retention_and_fresh_permissions() {
+        let settings = default_privacy_settings(1);
+        assert_eq!(privacy_score(&settings, false), SCORE_RETENTION_30_DAYS / 2 + SCORE_NO_STALE_PERMISSIONS);
+        assert_eq!(privacy_score(&settings, true), SCORE_RETENTION_30_DAYS / 2);
+    }
+
+    #[test]
+    fn every_factor_met_scores_one_hundred() {
+        let mut settings = default_privacy_settings(1);
+        settings.track_ip_address = false;
+        settings.track_user_agent = false;
+        settings.activity_log_retention_days = 30;
+        settings.auto_anonymize_after_days = Some(7);
+        settings.track_dashboard_activity = false;
+        settings.track_oauth_activity = false;
+        assert_eq!(privacy_score(&settings, false), 100);
+    }
+
+    #[test]
+    fn each_setting_moves_the_score_by_its_factor() {
+        let base = default_privacy_settings(1);
+        let score = |change: fn(&mut PrivacySettings)| {
+            let mut settings = base.clone();
+            change(&mut settings);
+            privacy_score(&settings, false) - privacy_score(&base, false)
+        };
+        assert_eq!(score(|s| s.track_ip_address = false), SCORE_IP_TRACKING_OFF);
+        assert_eq!(score(|s| s.track_user_agent = false), SCORE_USER_AGENT_TRACKING_OFF);
+        assert_eq!(score(|s| s.activity_log_retention_days = 30), SCORE_RETENTION_30_DAYS / 2);
+        assert_eq!(score(|s| s.auto_anonymize_after_days = Some(30)), SCORE_AUTO_ANONYMIZE);
+        assert_eq!(score(|s| s.track_dashboard_activity = false), SCORE_DASHBOARD_TRACKING_OFF);
+        assert_eq!(score(|s| s.track_oauth_activity = false), SCORE_OAUTH_TRACKING_OFF);
+    }
+
+    #[test]
+    fn retention_beyond_ninety_days_earns_no_retention_credit() {
+        let mut settings = default_privacy_settings(1);
+        settings.activity_log_retention_days = 91;
+        assert_eq!(privacy_score(&settings, false), SCORE_NO_STALE_PERMISSIONS);
+    }
+
+    #[test]
+    fn version_one_bundle_decodes_unchanged() {
+        let mut data = bundle();
+        data.login_activities = vec![login(1)];
+        let decoded = decode_export_format(data).unwrap();
+        assert_eq!(decoded.export_format_version, 1);
+        assert_eq!(decoded.login_activities, vec![login(1)]);
+    }
+
+    #[test]
+    fn unknown_export_format_is_rejected_by_name() {
+        for version in [0, EXPORT_FORMAT_VERSION + 1] {
+            let mut data = bundle();
+            data.export_format_version = version;
+            let error = decode_export_format(data).unwrap_err();
+            assert!(format!("{:?}", error).contains("UnsupportedExportFormat"));
+        }
+    }
+
+    fn planned_total(data: &ExportedData) -> u32 {
+        plan_import_counts(data).counts().iter().map(|(_, count)| count).sum()
+    }
+
+    #[test]
+    fn verify_import_matches_a_complete_import_and_flags_a_short_one() {
+        let exported = || {
+            let mut data = bundle();
+            data.login_activities = vec![login(1), login(2), login(3)];
+            data.oauth_activities = vec![oauth("app", 4)];
+            data
+        };
+        let expected = plan_import_counts(&exported());
+
+        // Full export without privacy settings: the import creates defaults
+        let mut imported = exported();
+        imported.privacy_settings = Some(default_privacy_settings(5));
+        let verification = compare_import_counts(&expected, &ImportPlan::from(&imported));
+        assert!(verification.all_match);
+        assert_eq!(verification.checks.len(), 11);
+
+        imported.login_activities.pop();
+        let verification = compare_import_counts(&expected, &ImportPlan::from(&imported));
+        assert!(!verification.all_match);
+        let mismatches: Vec<(&str, u32, u32)> = verification
+            .checks
+            .iter()
+            .filter(|check| !check.matches)
+            .map(|check| (check.entry_type.as_str(), check.expected, check.actual))
+            .collect();
+        assert_eq!(mismatches, vec![("login_activities", 3, 2)]);
+    }
+
+    #[test]
+    fn full_export_without_privacy_settings_plans_defaults() {
+        let data = bundle();
+        assert_eq!(plan_import_counts(&data).privacy_settings, 1);
+        assert_eq!(planned_total(&data), 1);
+        assert!(matches!(import_items(data).as_slice(), [ImportItem::PrivacySettings(None)]));
+    }
+
+    #[test]
+    fn selective_export_without_privacy_settings_leaves_them_alone() {
+        let mut data = bundle();
+        data.selected_types = Some(vec!["login_activities".into()]);
+        data.login_activities = vec![login(5_000)];
+        assert_eq!(plan_import_counts(&data).privacy_settings, 0);
+        assert_eq!(planned_total(&data), 1);
+        assert!(matches!(import_items(data).as_slice(), [ImportItem::LoginActivity(_)]));
+    }
+
+    #[test]
+    fn selective_export_of_privacy_settings_imports_them() {
+        let mut data = bundle();
+        data.selected_types = Some(vec!["privacy_settings".into()]);
+        assert_eq!(plan_import_counts(&data).privacy_settings, 1);
+        data.privacy_settings = Some(default_privacy_settings(1_000));
+        assert_eq!(plan_import_counts(&data).privacy_settings, 1);
+        assert!(matches!(import_items(data).as_slice(), [ImportItem::PrivacySettings(Some(_))]));
+    }
+
+    #[test]
+    fn import_then_store_keeps_activity_seq_strictly_increasing() {
+        // Agent already stored three logins (seq 0..=2); the bundle has gaps and a pre-seq entry
+        let mut exported = vec![login(9_000), login(4_000), login(1_000)];
+        exported[0].seq = Some(9);
+        exported[1].seq = Some(4);
+        let existing_links = 3;
+
+        // next_activity_seq is the number of links created so far, so each write takes the next one
+        let mut links_created = existing_links;
+        let mut next_seq = || {
+            links_created += 1;
+            links_created - 1
+        };
+        let imported: Vec<(i64, u64)> = in_store_order(exported, |activity| (activity.seq, activity.timestamp))
+            .into_iter()
+            .map(|activity| (activity.timestamp, next_seq()))
+            .collect();
+        let stored_after_import = next_seq();
+
+        assert_eq!(imported, vec![(1_000, 3), (4_000, 4), (9_000, 5)]);
+        assert_eq!(stored_after_import, 6);
+    }
+
+    fn agent_key(byte: u8) -> AgentPubKey {
+        AgentPubKey::from_raw_36(vec![byte; 36])
+    }
+
+    #[test]
+    fn import_rejects_a_bundle_claiming_a_foreign_author() {
+        let importing_agent = agent_key(1);
+        assert!(import_author_problem(Some(&agent_key(2)), DNA_VERSION, &importing_agent).is_some());
+        assert!(import_author_problem(Some(&agent_key(2)), "1.11", &importing_agent).is_some());
+        assert!(import_author_problem(Some(&importing_agent), DNA_VERSION, &importing_agent).is_none());
+    }
+
+    #[test]
+    fn import_requires_exported_by_once_exports_record_it() {
+        let importing_agent = agent_key(1);
+        assert!(import_author_problem(None, DNA_VERSION, &importing_agent).is_some());
+        assert!(import_author_problem(None, "garbage", &importing_agent).is_some());
+        // Pre-1.12 exports (the v1.11 migration path) never carried the field
+        assert!(import_author_problem(None, "1.11", &importing_agent).is_none());
+    }
+
+    #[test]
+    fn exported_by_requirement_compares_major_and_minor() {
+        let importing_agent = agent_key(1);
+        // A later major records exported_by even though its minor is lower
+        assert!(import_author_problem(None, "2.0", &importing_agent).is_some());
+        assert!(import_author_problem(None, "2.3", &importing_agent).is_some());
+        assert!(import_author_problem(None, "0.99", &importing_agent).is_none());
+        // Patch versions don't hide the minor
+        assert!(import_author_problem(None, "1.12.1", &importing_agent).is_some());
+        assert!(import_author_problem(None, "1.11.4", &importing_agent).is_none());
+    }
+
+    #[test]
+    fn dna_major_minor_ignores_the_patch() {
+        assert_eq!(dna_major_minor("1.12"), Some((1, 12)));
+        assert_eq!(dna_major_minor("1.12.7"), Some((1, 12)));
+        assert_eq!(dna_major_minor("1"), None);
+        assert_eq!(dna_major_minor("1.x"), None);
+    }
+
+    /// One entry of each type; the match is exhaustive so a new entry type needs a sample
+    fn sample_entry(entry_type: UnitEntryTypes) -> EntryTypes {
+        match entry_type {
+            UnitEntryTypes::UserProfile => EntryTypes::UserProfile(profile(1, 1)),
+            UnitEntryTypes::RecoveryPhrase => EntryTypes::RecoveryPhrase(RecoveryPhrase {
+                encrypted_mnemonic: "bW5lbW9uaWM=".into(),
+                nonce: "bm9uY2U=".into(),
+                salt: "c2FsdA==".into(),
+                tag: "dGFn".into(),
+                verified: false,
+                created_at: 1,
+            }),
+            UnitEntryTypes::Session => EntryTypes::Session(Session {
+                user_agent: "Mozilla/5.0".into(),
+                ip_address: String::new(),
+                device_info: String::new(),
+                conductor_id: "edge-1".into(),
+                created_at: 1,
+                last_active: 1,
+                is_current: false,
+            }),
+            UnitEntryTypes::EmailPermission => EntryTypes::EmailPermission(permission("billing", true, None)),
+            UnitEntryTypes::LoginActivity => EntryTypes::LoginActivity(login(1)),
+            UnitEntryTypes::DashboardActivity => EntryTypes::DashboardActivity(DashboardActivity {
+                visit_timestamp: 1,
+                page_path: "/dashboard".into(),
+                duration_seconds: None,
+                created_at: 1,
+                seq: None,
+            }),
+            UnitEntryTypes::OAuthActivity => EntryTypes::OAuthActivity(oauth("app-1", 1)),
+            UnitEntryTypes::PrivacySettings => EntryTypes::PrivacySettings(default_privacy_settings(1)),
+            UnitEntryTypes::AppAnalyticsId => EntryTypes::AppAnalyticsId(AppAnalyticsId {
+                app_id: "app-1".into(),
+                analytics_id: "analytics-1".into(),
+                created_at: 1,
+            }),
+            UnitEntryTypes::TotpConfig => EntryTypes::TotpConfig(TotpConfig {
+                encrypted_secret: String::new(),
+                nonce: String::new(),
+                salt: String::new(),
+                tag: String::new(),
+                encrypted_backup_codes: String::new(),
+                backup_nonce: String::new(),
+                backup_salt: String::new(),
+                backup_tag: String::new(),
+                enabled: true,
+                created_at: 1,
+                updated_at: 1,
+            }),
+            UnitEntryTypes::ProfilePicture => EntryTypes::ProfilePicture(ProfilePicture {
+                profile_picture: "data:image/png;base64,".into(),
+                has_custom_picture: false,
+                updated_at: 1,
+            }),
+            UnitEntryTypes::ImportCheckpoint => EntryTypes::ImportCheckpoint(checkpoint(1, 1, 0)),
+        }
+    }
+
+    #[test]
+    fn every_entry_type_has_a_schema_matching_its_serialized_fields() {
+        let schemas = entry_schemas();
+        let names: std::collections::BTreeSet<&str> = schemas.iter().map(|schema| schema.entry_type.as_str()).collect();
+        assert_eq!(names.len(), ENTRY_TYPES.len());
+
+        for (entry_type, schema) in ENTRY_TYPES.into_iter().zip(&schemas) {
+            let serialized = holochain_serialized_bytes::encode(&sample_entry(entry_type)).unwrap();
+            let mut keys: std::collections::BTreeMap<String, serde::de::IgnoredAny> =
+                holochain_serialized_bytes::decode(&serialized).unwrap();
+            keys.remove("type");
+
+            let schema_fields: std::collections::BTreeSet<&str> = schema.fields.iter().map(|field| field.name.as_str()).collect();
+            let entry_fields: std::collections::BTreeSet<&str> = keys.keys().map(String::as_str).collect();
+            assert_eq!(schema_fields, entry_fields, "schema for {} is out of date", schema.entry_type);
+        }
+    }
+
+    fn checkpoint(export_timestamp: i64, total: u32, next_index: u32) -> ImportCheckpoint {
+        ImportCheckpoint { export_timestamp, total, next_index, created_at: 1, updated_at: 1 }
+    }
+
+    #[test]
+    fn resume_index_continues_the_same_bundle() {
+        assert_eq!(resume_index(None, 500, 10), 0);
+        assert_eq!(resume_index(Some(&checkpoint(500, 10, 4)), 500, 10), 4);
+        assert_eq!(resume_index(Some(&checkpoint(500, 10, 10)), 500, 10), 10);
+    }
+
+    #[test]
+    fn resume_index_restarts_for_a_different_bundle() {
+        assert_eq!(resume_index(Some(&checkpoint(500, 10, 4)), 600, 10), 0);
+        assert_eq!(resume_index(Some(&checkpoint(500, 10, 4)), 500, 12), 0);
+    }
+
+    /// Source chain action at `action_hash(seq)`: the Dna action at 0, then Creates each pointing back one
+    fn chain_action(seq: u8, author: AgentPubKey) -> SignedActionHashed {
+        let action = if seq == 0 {
+            Action::Dna(Dna {
+                author,
+                timestamp: Timestamp::from_micros(0),
+                hash: DnaHash::from_raw_36(vec![0xdd; 36]),
+            })
+        } else {
+            Action::Create(Create {
+                author,
+                timestamp: Timestamp::from_micros(seq as i64),
+                action_seq: seq as u32,
+                prev_action: action_hash(seq - 1),
+                entry_type: EntryType::App(AppEntryDef::new(0.into(), 0.into(), EntryVisibility::Private)),
+                entry_hash: EntryHash::from_raw_36(vec![0xee; 36]),
+                weight: Default::default(),
+            })
+        };
+        signed(action, action_hash(seq))
+    }
+
+    #[test]
+    fn healthy_chain_verifies_back_to_the_dna_action() {
+        let healthy = |hash: &ActionHash| {
+            let seq = hash.get_raw_36()[0];
+            (seq <= 4).then(|| chain_action(seq, author()))
+        };
+        assert!(chain_is_intact(&action_hash(4), healthy));
+        assert!(chain_is_intact(&action_hash(0), healthy));
+        assert!(!chain_is_intact(&action_hash(9), healthy));
+    }
+
+    #[test]
+    fn chain_with_a_missing_action_or_another_author_is_broken() {
+        let missing = |hash: &ActionHash| {
+            let seq = hash.get_raw_36()[0];
+            (seq <= 4 && seq != 2).then(|| chain_action(seq, author()))
+        };
+        assert!(!chain_is_intact(&action_hash(4), missing));
+
+        let foreign = |hash: &ActionHash| {
+            let seq = hash.get_raw_36()[0];
+            let author = if seq == 1 { agent_key(7) } else { author() };
+            (seq <= 4).then(|| chain_action(seq, author))
+        };
+        assert!(!chain_is_intact(&action_hash(4), foreign));
+    }
+
+    #[test]
+    fn backup_manifest_covers_every_created_entry() {
+        // One create per entry type, plus an update of the profile
+        let mut chain: Vec<(String, ActionHash)> = ENTRY_TYPES
+            .iter()
+            .enumerate()
+            .map(|(index, entry_type)| (format!("{:?}", entry_type), action_hash(index as u8 + 1)))
+            .collect();
+        chain.push((format!("{:?}", UnitEntryTypes::UserProfile), action_hash(50)));
+
+        let manifest = build_backup_manifest(|entry_type| {
+            let name = format!("{:?}", entry_type);
+            Ok(chain.iter().filter(|(stored, _)| *stored == name).map(|(_, hash)| hash.clone()).collect())
+        })
+        .unwrap();
+
+        let checkpoint = format!("{:?}", UnitEntryTypes::ImportCheckpoint);
+        let user_data = chain.iter().filter(|(name, _)| *name != checkpoint).count() as u32;
+        assert_eq!(manifest.total, user_data);
+        assert_eq!(manifest.user_profile.len(), 2);
+        assert_eq!(manifest.profile_picture.len(), 1);
+    }
+
+    #[test]
+    fn delete_account_purges_every_link_type() {
+        // Every getter reads through an AgentTo* link, so purging every link type leaves them all empty
+        let mut purged = Vec::new();
+        let report = build_link_audit(|link_type| {
+            purged.push(link_type);
+            Ok(1)
+        }).unwrap();
+
+        let all_link_types: Vec<LinkTypes> = LinkTypes::iter().collect();
+        assert_eq!(purged.len(), all_link_types.len());
+        for link_type in all_link_types {
+            assert!(purged.contains(&link_type), "{:?} is not purged", link_type);
+        }
+        assert_eq!(report.total, purged.len() as u32);
+    }
+
+    #[test]
+    fn undeleted_creates_skips_deleted_entries() {
+        let creates = vec![action_hash(1), action_hash(2), action_hash(3)];
+        let deletes = vec![action_hash(2), action_hash(9)];
+        assert_eq!(undeleted_creates(creates, &deletes), vec![action_hash(1), action_hash(3)]);
+    }
+
+    #[test]
+    fn undeleted_creates_is_empty_after_full_erasure() {
+        let creates = vec![action_hash(1), action_hash(2)];
+        assert!(undeleted_creates(creates.clone(), &creates).is_empty());
+    }
+
+    #[test]
+    fn storage_estimate_grows_as_entries_are_added() {
+        let one = entries_size(vec![login(1)]).unwrap();
+        let two = entries_size(vec![login(1), login(2)]).unwrap();
+        assert_eq!(entries_size(Vec::<LoginActivity>::new()).unwrap(), 0);
+        assert!(one > 0);
+        assert!(two > one);
+
+        let mut report = StorageReport { login_activity_bytes: one, ..Default::default() };
+        let before = report.sum_of_types();
+        report.login_activity_bytes = two;
+        report.sessions_bytes = 7;
+        assert_eq!(report.sum_of_types(), before + (two - one) + 7);
+    }
+
+    #[test]
+    fn activity_storage_lists_the_larger_history_first() {
+        let logins: Vec<LoginActivity> = (1..=20).map(login).collect();
+        let sizes = activity_storage(logins, vec![], vec![oauth("app", 1)]).unwrap();
+
+        let names: Vec<&str> = sizes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["login_activity", "oauth_activity", "dashboard_activity"]);
+        assert!(sizes[0].1 > sizes[1].1);
+        assert_eq!(sizes[2].1, 0);
+    }
+
+    fn consent(event_type: &str, timestamp: i64, scopes: &[&str]) -> OAuthActivity {
+        let mut activity = oauth("app", timestamp);
+        activity.event_type = event_type.into();
+        activity.scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        activity
+    }
+
+    #[test]
+    fn scopes_survive_an_entry_round_trip() {
+        let granted = consent("consent_granted", 1, &["email", "profile"]);
+        let bytes = SerializedBytes::try_from(granted.clone()).unwrap();
+        assert_eq!(OAuthActivity::try_from(bytes).unwrap(), granted);
+    }
+
+    #[test]
+    fn app_scopes_come_from_the_latest_consent() {
+        let activities = vec![
+            consent("consent_granted", 100, &["email"]),
+            consent("consent_granted", 300, &["email", "profile"]),
+            consent("login", 400, &[]),
+            consent("consent_granted", 200, &["openid"]),
+        ];
+        assert_eq!(latest_consent_scopes(activities), vec!["email".to_string(), "profile".to_string()]);
+    }
+
+    #[test]
+    fn app_scopes_are_empty_after_a_revocation_until_consent_is_granted_again() {
+        let revoked = vec![consent("consent_granted", 100, &["email"]), consent("revoked", 200, &[])];
+        assert!(latest_consent_scopes(revoked.clone()).is_empty());
+        
+        let mut regranted = revoked;
+        regranted.push(consent("consent_granted", 300, &["profile"]));
+        assert_eq!(latest_consent_scopes(regranted), vec!["profile".to_string()]);
+        
+        assert!(latest_consent_scopes(vec![consent("login", 100, &[])]).is_empty());
+    }
+
+    #[test]
+    fn bounds_widen_to_cover_every_included_timestamp() {
+        let mut bounds = TimestampBounds::default();
+        assert_eq!(bounds, TimestampBounds { first: None, last: None });
+        
+        bounds.include(200);
+        assert_eq!(bounds, TimestampBounds { first: Some(200), last: Some(200) });
+        
+        for timestamp in [300, 100, 250] {
+            bounds.include(timestamp);
+        }
+        assert_eq!(bounds, TimestampBounds { first: Some(100), last: Some(300) });
+    }
+
+    #[test]
+    fn login_bounds_read_tags_and_only_fetch_untagged_links() {
+        let links = vec![
+            activity_link(1, timestamp_tag(500)),
+            activity_link(2, LinkTag::new(vec![])),
+            activity_link(3, timestamp_tag(200)),
+        ];
+        let mut fetched = Vec::new();
+        let bounds = login_bounds(links, |link| {
+            fetched.push(link.target.clone());
+            Ok(Some(login(50)))
+        })
+        .unwrap();
+        
+        assert_eq!(fetched, vec![AnyLinkableHash::from(action_hash(2))]);
+        assert_eq!(bounds, TimestampBounds { first: Some(50), last: Some(500) });
+    }
+
+    #[test]
+    fn login_bounds_skip_untagged_links_whose_entry_is_gone() {
+        let links = vec![activity_link(1, LinkTag::new(vec![])), activity_link(2, timestamp_tag(300))];
+        let bounds = login_bounds(links, |_| Ok(None)).unwrap();
+        assert_eq!(bounds, TimestampBounds { first: Some(300), last: Some(300) });
+        assert_eq!(login_bounds(vec![], |_| Ok(None)).unwrap(), TimestampBounds::default());
+    }
+
+    #[test]
+    fn record_size_is_its_serialized_app_entry() {
+        let bytes = SerializedBytes::try_from(login(1)).unwrap();
+        let size = bytes.bytes().len() as u64;
+        assert_eq!(record_entry_size(&record_with(1, app_entry(bytes))), size);
+        assert_eq!(record_entry_size(&record_with(2, None)), 0);
+    }
+}
@@ -0,0 +1,990 @@
+use hdi::prelude::*;
+
+/// Encrypted user profile - stored ONLY on private DHT
+/// Binary data stored as base64 strings for serialization compatibility
+/// v1.7: Added username field for privacy-friendly login
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct UserProfile {
+    pub encrypted_email: String,   // Base64-encoded encrypted email
+    pub nonce: String,             // Base64-encoded nonce
+    pub salt: String,              // Base64-encoded KDF salt
+    pub tag: String,               // Base64-encoded authentication tag
+    pub username: Option<String>,  // ✅ NEW v1.7: Optional username (encrypted)
+    pub display_name: String,      // Can be public
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Encrypted recovery phrase - stored ONLY on private DHT
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct RecoveryPhrase {
+    pub encrypted_mnemonic: String,  // Base64-encoded encrypted 24-word phrase
+    pub nonce: String,
+    pub salt: String,
+    pub tag: String,
+    pub verified: bool,               // Has user verified they saved it?
+    pub created_at: i64,
+}
+
+/// Session tracking - stored ONLY on private DHT
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct Session {
+    pub user_agent: String,
+    pub ip_address: String,
+    pub device_info: String,
+    pub conductor_id: String,         // Which edge node
+    pub created_at: i64,
+    pub last_active: i64,
+    #[serde(default)]
+    pub is_current: bool,             // The device the user is on right now (set via set_current_session)
+}
+
+/// What a service uses email consent for, so permissions can be grouped in the UI
+/// Entries written before categories existed deserialize as Other
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PermissionCategory {
+    Transactional,  // Invoices, receipts, account changes
+    Security,       // Login alerts, 2FA, recovery
+    Marketing,
+    Support,
+    #[default]
+    Other,
+}
+
+/// Email permission - NEW IN v1.1
+/// Stores user consent for Flowsta services to access their email for specific purposes
+/// This enables privacy-preserving email notifications (invoices, system alerts, etc.)
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct EmailPermission {
+    pub service_name: String,         // e.g., "billing", "support", "security_alerts"
+    pub purpose: String,              // Human-readable: "Send monthly invoice notifications"
+    pub granted: bool,                // User consent status
+    pub granted_at: Option<i64>,      // When permission was granted (None if never granted)
+    pub revoked_at: Option<i64>,      // When permission was revoked (None if still granted)
+    pub last_used_at: Option<i64>,    // When service last accessed email (for transparency)
+    #[serde(default)]
+    pub expires_at: Option<i64>,      // When consent lapses (None = never expires)
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub category: PermissionCategory,
+}
+
+/// Login activity - NEW IN v1.6
+/// User-owned login tracking with privacy controls
+/// IP and user-agent are optional - user can disable tracking
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct LoginActivity {
+    pub timestamp: i64,
+    pub login_method: String,         // "password" or "sso"
+    pub ip_address: Option<String>,   // User can opt-out (privacy setting)
+    pub user_agent: Option<String>,   // User can opt-out (privacy setting)
+    pub session_id: String,
+    pub created_at: i64,
+    #[serde(default)]
+    pub seq: Option<u64>,             // Per-type store order, set by the coordinator (None on older entries)
+    #[serde(default)]
+    pub region: Option<String>,       // Coarse location hint (e.g., country code) resolved by the frontend
+}
+
+/// Dashboard activity - NEW IN v1.6
+/// Track dashboard page visits for user's own analytics
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct DashboardActivity {
+    pub visit_timestamp: i64,
+    pub page_path: String,            // e.g., "/dashboard/apps", "/dashboard/analytics"
+    pub duration_seconds: Option<i64>, // Filled in by frontend
+    pub created_at: i64,
+    #[serde(default)]
+    pub seq: Option<u64>,             // Per-type store order, set by the coordinator (None on older entries)
+}
+
+/// OAuth activity - NEW IN v1.6
+/// Track OAuth app usage per user (user-owned, not for billing)
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct OAuthActivity {
+    pub timestamp: i64,
+    pub app_id: String,
+    pub app_name: String,
+    pub event_type: String,           // "login", "consent_granted", "token_refreshed", "revoked"
+    pub created_at: i64,
+    #[serde(default)]
+    pub seq: Option<u64>,             // Per-type store order, set by the coordinator (None on older entries)
+    #[serde(default)]
+    pub scopes: Vec<String>,          // Scopes granted on "consent_granted" events (empty otherwise)
+}
+
+/// Privacy settings - NEW IN v1.6
+/// User controls for activity tracking
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct PrivacySettings {
+    pub track_ip_address: bool,
+    pub track_user_agent: bool,
+    pub activity_log_retention_days: i64,
+    pub auto_anonymize_after_days: Option<i64>,  // Future: hash old IPs after N days
+    #[serde(default = "default_page_size")]
+    pub default_page_size: u32,                  // Activity getters use this when no limit is given
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: u32,                       // store_session evicts the oldest beyond this cap
+    #[serde(default = "default_track_activity")]
+    pub track_dashboard_activity: bool,          // store_dashboard_activity is a no-op when false
+    #[serde(default = "default_track_activity")]
+    pub track_oauth_activity: bool,              // store_oauth_activity is a no-op when false
+    #[serde(default = "default_track_activity")]
+    pub track_region: bool,                      // store_login_activity drops region when false
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Page size used by activity getters when neither the caller nor the settings specify one
+/// Also fills in default_page_size for PrivacySettings entries written before the field existed
+pub fn default_page_size() -> u32 {
+    100
+}
+
+/// Maximum number of live sessions kept before store_session evicts the oldest
+/// Also fills in max_sessions for PrivacySettings entries written before the field existed
+pub fn default_max_sessions() -> u32 {
+    10
+}
+
+/// Dashboard and OAuth activity tracking is on unless the user opts out
+/// Also fills in the toggles for PrivacySettings entries written before they existed
+pub fn default_track_activity() -> bool {
+    true
+}
+
+/// App Analytics ID - NEW IN v1.9
+/// Zero-knowledge analytics: stores random analytics_id per app
+/// This ID is mathematically impossible to link to user DID without user's password
+/// Only user can decrypt this from their private Holochain with their password
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct AppAnalyticsId {
+    pub app_id: String,           // UUID of developer app
+    pub analytics_id: String,     // Random UUID - no link to user DID
+    pub created_at: i64,          // Timestamp when first created
+}
+
+/// TOTP Config - NEW IN v1.10
+/// Encrypted TOTP secret for QR-based Two-Factor Authentication
+/// The secret is encrypted with the user's password (same pattern as encrypted_email/recovery_phrase)
+/// Backup codes are one-time-use, stored as encrypted comma-separated string
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct TotpConfig {
+    pub encrypted_secret: String,       // Base64 AES-256-GCM encrypted TOTP secret
+    pub nonce: String,                  // Base64 encryption nonce
+    pub salt: String,                   // Base64 KDF salt
+    pub tag: String,                    // Base64 auth tag
+    pub encrypted_backup_codes: String, // Base64 encrypted backup codes (comma-separated)
+    pub backup_nonce: String,           // Separate nonce for backup codes
+    pub backup_salt: String,            // Separate salt for backup codes
+    pub backup_tag: String,             // Separate auth tag for backup codes
+    pub enabled: bool,                  // Is 2FA currently active?
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Profile Picture - NEW IN v1.11
+/// Moved from public identity DNA to private DNA for pseudonymity
+/// Profile pictures (especially custom uploads) are identifiable and should not be on the public DHT
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct ProfilePicture {
+    pub profile_picture: String,      // Base64 data URI (identicon or custom upload)
+    pub has_custom_picture: bool,     // True if user-uploaded (not auto-generated identicon)
+    pub updated_at: i64,
+}
+
+/// Import Checkpoint
+/// How far import_resume got through a bundle, so a retry without from_index picks up where it stopped
+/// The bundle is identified by its export_timestamp and item count
+#[hdk_entry_helper]
+#[derive(Clone, PartialEq)]
+pub struct ImportCheckpoint {
+    pub export_timestamp: i64,        // export_timestamp of the bundle being imported
+    pub total: u32,                   // Item count of that bundle
+    pub next_index: u32,              // First item not yet imported
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Entry types with PRIVATE visibility
+/// CRITICAL: visibility = "private" means NOT on public DHT
+#[hdk_entry_types]
+#[unit_enum(UnitEntryTypes)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EntryTypes {
+    #[entry_type(visibility = "private")]
+    UserProfile(UserProfile),
+    
+    #[entry_type(visibility = "private")]
+    RecoveryPhrase(RecoveryPhrase),
+    
+    #[entry_type(visibility = "private")]
+    Session(Session),  // DEPRECATED but kept for backward compatibility
+    
+    #[entry_type(visibility = "private")]
+    EmailPermission(EmailPermission),  // NEW IN v1.1
+    
+    // NEW IN v1.6 - User-owned metadata
+    #[entry_type(visibility = "private")]
+    LoginActivity(LoginActivity),
+    
+    #[entry_type(visibility = "private")]
+    DashboardActivity(DashboardActivity),
+    
+    #[entry_type(visibility = "private")]
+    OAuthActivity(OAuthActivity),
+    
+    #[entry_type(visibility = "private")]
+    PrivacySettings(PrivacySettings),
+    
+    // NEW IN v1.9 - Zero-knowledge analytics
+    #[entry_type(visibility = "private")]
+    AppAnalyticsId(AppAnalyticsId),
+    
+    // NEW IN v1.10 - Two-Factor Authentication
+    #[entry_type(visibility = "private")]
+    TotpConfig(TotpConfig),
+
+    // NEW IN v1.11 - Profile picture (moved from public identity DNA)
+    #[entry_type(visibility = "private")]
+    ProfilePicture(ProfilePicture),
+
+    // Resumable import progress
+    #[entry_type(visibility = "private")]
+    ImportCheckpoint(ImportCheckpoint),
+}
+
+/// Link types for private data
+#[derive(Serialize, Deserialize)]
+#[hdk_link_types]
+pub enum LinkTypes {
+    AgentToProfile,
+    AgentToRecoveryPhrase,
+    AgentToSessions,  // DEPRECATED but kept for backward compatibility
+    AgentToEmailPermissions,  // NEW IN v1.1
+    // NEW IN v1.6 - User-owned metadata
+    AgentToLoginActivity,
+    AgentToDashboardActivity,
+    AgentToOAuthActivity,
+    AgentToPrivacySettings,
+    // NEW IN v1.9 - Zero-knowledge analytics
+    AgentToAppAnalyticsId,
+    // NEW IN v1.10 - Two-Factor Authentication
+    AgentToTotpConfig,
+    // NEW IN v1.11 - Profile picture (moved from public identity DNA)
+    AgentToProfilePicture,
+    // Resumable import progress
+    AgentToImportCheckpoint,
+}
+
+/// Validate the app entry carried by a Create/Update record
+/// Private entries are only present for the author, so hidden entries are skipped
+fn validate_record_entry(record: &Record) -> ExternResult<ValidateCallbackResult> {
+    match record_app_entry(record)? {
+        Some(entry) => Ok(validate_entry(&entry)),
+        None => Ok(ValidateCallbackResult::Valid),
+    }
+}
+
+/// Decode the app entry of a record; None if the entry is hidden or not one of ours
+fn record_app_entry(record: &Record) -> ExternResult<Option<EntryTypes>> {
+    match (record.action().entry_type(), record.entry().as_option()) {
+        (Some(entry_type), Some(entry)) => decode_app_entry(entry_type, entry),
+        _ => Ok(None),
+    }
+}
+
+/// Decode an entry of the given type; None if it isn't one of this zome's app entries
+fn decode_app_entry(entry_type: &EntryType, entry: &Entry) -> ExternResult<Option<EntryTypes>> {
+    let EntryType::App(app_entry_def) = entry_type else {
+        return Ok(None);
+    };
+    EntryTypes::deserialize_from_type(app_entry_def.zome_index, app_entry_def.entry_index, entry)
+}
+
+/// Reject updates that rewrite fields which must never change after creation
+/// Skipped when either entry isn't visible (private entries outside the author's node)
+fn validate_update_entry(original_record: &Record, entry_type: &EntryType, entry: &Entry) -> ExternResult<ValidateCallbackResult> {
+    let (Some(original), Some(updated)) = (record_app_entry(original_record)?, decode_app_entry(entry_type, entry)?) else {
+        return Ok(ValidateCallbackResult::Valid);
+    };
+    Ok(validate_immutable_fields(&original, &updated))
+}
+
+/// UserProfile created_at is the account age and never changes
+/// RecoveryPhrase created_at is re-stamped on rotation (new mnemonic) and on verification,
+/// so it may only change alongside one of those
+fn validate_immutable_fields(original: &EntryTypes, updated: &EntryTypes) -> ValidateCallbackResult {
+    match (original, updated) {
+        (EntryTypes::UserProfile(original), EntryTypes::UserProfile(updated))
+            if original.created_at != updated.created_at =>
+        {
+            ValidateCallbackResult::Invalid("UserProfile created_at cannot change on update".into())
+        }
+        (EntryTypes::RecoveryPhrase(original), EntryTypes::RecoveryPhrase(updated)) => {
+            let is_rotation = original.encrypted_mnemonic != updated.encrypted_mnemonic
+                || original.verified != updated.verified;
+            if !is_rotation && original.created_at != updated.created_at {
+                return ValidateCallbackResult::Invalid(
+                    "RecoveryPhrase created_at can only change when the phrase is rotated or verified".into()
+                );
+            }
+            ValidateCallbackResult::Valid
+        }
+        _ => ValidateCallbackResult::Valid,
+    }
+}
+
+/// Rules shared by every entry type, then the type-specific ones
+fn validate_entry(entry: &EntryTypes) -> ValidateCallbackResult {
+    if entry_created_at(entry).is_some_and(|created_at| created_at <= 0) {
+        return ValidateCallbackResult::Invalid(
+            "created_at must be set to a positive timestamp".into()
+        );
+    }
+
+    match entry {
+        EntryTypes::UserProfile(profile) => validate_user_profile(profile),
+        EntryTypes::EmailPermission(permission) => validate_email_permission(permission),
+        EntryTypes::Session(session) => validate_session(session),
+        EntryTypes::OAuthActivity(activity) => validate_oauth_activity(activity),
+        EntryTypes::PrivacySettings(settings) => validate_privacy_settings(settings),
+        _ => ValidateCallbackResult::Valid,
+    }
+}
+
+/// created_at of an entry, for types that have one (ProfilePicture only tracks updated_at)
+fn entry_created_at(entry: &EntryTypes) -> Option<i64> {
+    match entry {
+        EntryTypes::UserProfile(profile) => Some(profile.created_at),
+        EntryTypes::RecoveryPhrase(recovery_phrase) => Some(recovery_phrase.created_at),
+        EntryTypes::Session(session) => Some(session.created_at),
+        EntryTypes::EmailPermission(permission) => Some(permission.created_at),
+        EntryTypes::LoginActivity(activity) => Some(activity.created_at),
+        EntryTypes::DashboardActivity(activity) => Some(activity.created_at),
+        EntryTypes::OAuthActivity(activity) => Some(activity.created_at),
+        EntryTypes::PrivacySettings(settings) => Some(settings.created_at),
+        EntryTypes::AppAnalyticsId(analytics_id) => Some(analytics_id.created_at),
+        EntryTypes::TotpConfig(totp) => Some(totp.created_at),
+        EntryTypes::ProfilePicture(_) => None,
+        EntryTypes::ImportCheckpoint(checkpoint) => Some(checkpoint.created_at),
+    }
+}
+
+/// A profile cannot be modified before it was created (breaks "last modified" displays)
+fn validate_user_profile(profile: &UserProfile) -> ValidateCallbackResult {
+    if profile.updated_at < profile.created_at {
+        return ValidateCallbackResult::Invalid(
+            "UserProfile updated_at cannot be earlier than created_at".into()
+        );
+    }
+    ValidateCallbackResult::Valid
+}
+
+/// EmailPermission consent state must be self-consistent:
+/// - granted => revoked_at is None
+/// - not granted but previously granted => revoked_at is Some
+fn validate_email_permission(permission: &EmailPermission) -> ValidateCallbackResult {
+    if permission.granted && permission.revoked_at.is_some() {
+        return ValidateCallbackResult::Invalid(
+            "Granted permission cannot have revoked_at set".into()
+        );
+    }
+    if !permission.granted && permission.granted_at.is_some() && permission.revoked_at.is_none() {
+        return ValidateCallbackResult::Invalid(
+            "Previously granted permission that is no longer granted must have revoked_at set".into()
+        );
+    }
+    ValidateCallbackResult::Valid
+}
+
+/// Session can't have been active before it was created
+fn validate_session(session: &Session) -> ValidateCallbackResult {
+    if session.last_active < session.created_at {
+        return ValidateCallbackResult::Invalid(
+            "Session last_active cannot be earlier than created_at".into()
+        );
+    }
+    ValidateCallbackResult::Valid
+}
+
+/// A max_sessions of 0 would leave store_session nothing to keep, so at least one session is required
+/// A default_page_size of 0 would make every activity getter return an empty page
+fn validate_privacy_settings(settings: &PrivacySettings) -> ValidateCallbackResult {
+    if settings.max_sessions == 0 {
+        return ValidateCallbackResult::Invalid(
+            "PrivacySettings max_sessions must be at least 1".into()
+        );
+    }
+    if settings.default_page_size == 0 {
+        return ValidateCallbackResult::Invalid(
+            "PrivacySettings default_page_size must be at least 1".into()
+        );
+    }
+    ValidateCallbackResult::Valid
+}
+
+/// Max length for OAuth app identifiers
+pub const MAX_APP_ID_LEN: usize = 128;
+
+/// app_id is an analytics grouping key, so it must be a non-empty, bounded identifier
+/// Allowed characters: ASCII letters, digits, '-', '_', '.', ':'
+fn validate_oauth_activity(activity: &OAuthActivity) -> ValidateCallbackResult {
+    if activity.app_id.is_empty() {
+        return ValidateCallbackResult::Invalid("OAuthActivity app_id cannot be empty".into());
+    }
+    if activity.app_id.len() > MAX_APP_ID_LEN {
+        return ValidateCallbackResult::Invalid(format!(
+            "OAuthActivity app_id exceeds {} characters", MAX_APP_ID_LEN
+        ));
+    }
+    let is_allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':');
+    if !activity.app_id.chars().all(is_allowed) {
+        return ValidateCallbackResult::Invalid(
+            "OAuthActivity app_id contains invalid characters".into()
+        );
+    }
+    ValidateCallbackResult::Valid
+}
+
+/// Whether an entry type is the given app entry type
+fn is_entry_type(entry_type: &EntryType, expected: UnitEntryTypes) -> ExternResult<bool> {
+    Ok(entry_type_matches(entry_type, &ScopedEntryDefIndex::try_from(expected)?))
+}
+
+/// Whether an entry type is the app entry type at the given scoped index
+fn entry_type_matches(entry_type: &EntryType, expected: &ScopedEntryDefIndex) -> bool {
+    let EntryType::App(app_entry_def) = entry_type else {
+        return false;
+    };
+    app_entry_def.zome_index == expected.zome_index
+        && app_entry_def.entry_index == expected.zome_type
+}
+
+/// Whether an entry type is PrivacySettings
+fn is_privacy_settings_type(entry_type: &EntryType) -> ExternResult<bool> {
+    is_entry_type(entry_type, UnitEntryTypes::PrivacySettings)
+}
+
+/// Entry type every link of the given type must point at
+fn link_target_type(link_type: &LinkTypes) -> UnitEntryTypes {
+    match link_type {
+        LinkTypes::AgentToProfile => UnitEntryTypes::UserProfile,
+        LinkTypes::AgentToRecoveryPhrase => UnitEntryTypes::RecoveryPhrase,
+        LinkTypes::AgentToSessions => UnitEntryTypes::Session,
+        LinkTypes::AgentToEmailPermissions => UnitEntryTypes::EmailPermission,
+        LinkTypes::AgentToLoginActivity => UnitEntryTypes::LoginActivity,
+        LinkTypes::AgentToDashboardActivity => UnitEntryTypes::DashboardActivity,
+        LinkTypes::AgentToOAuthActivity => UnitEntryTypes::OAuthActivity,
+        LinkTypes::AgentToPrivacySettings => UnitEntryTypes::PrivacySettings,
+        LinkTypes::AgentToAppAnalyticsId => UnitEntryTypes::AppAnalyticsId,
+        LinkTypes::AgentToTotpConfig => UnitEntryTypes::TotpConfig,
+        LinkTypes::AgentToProfilePicture => UnitEntryTypes::ProfilePicture,
+        LinkTypes::AgentToImportCheckpoint => UnitEntryTypes::ImportCheckpoint,
+    }
+}
+
+/// Every link is AgentTo*: the base must be the author's own key and the target an action
+/// creating (or updating) the entry type that link type stands for
+fn validate_create_link(create_link: &CreateLink) -> ExternResult<ValidateCallbackResult> {
+    let target_hash = match link_target_action(create_link) {
+        Ok(target_hash) => target_hash,
+        Err(invalid) => return Ok(invalid),
+    };
+
+    let Some(link_type) = LinkTypes::from_type(create_link.zome_index, create_link.link_type)? else {
+        return Ok(ValidateCallbackResult::Invalid("Unknown link type".into()));
+    };
+
+    let target = must_get_valid_record(target_hash)?;
+    let expected = link_target_type(&link_type);
+    let is_expected_type = match target.action().entry_type() {
+        Some(entry_type) => is_entry_type(entry_type, expected)?,
+        None => false,
+    };
+    if !is_expected_type {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "{:?} link target must be a {:?} entry", link_type, expected
+        )));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// The checks that need no DHT lookup: the base is the author's own key and the target is an action
+fn link_target_action(create_link: &CreateLink) -> Result<ActionHash, ValidateCallbackResult> {
+    if create_link.base_address != AnyLinkableHash::from(create_link.author.clone()) {
+        return Err(ValidateCallbackResult::Invalid(
+            "Link base must be the author's agent key".into()
+        ));
+    }
+    ActionHash::try_from(create_link.target_address.clone())
+        .map_err(|_| ValidateCallbackResult::Invalid("Link target must be an action hash".into()))
+}
+
+/// Each agent may only hold one live PrivacySettings entry; later changes must be updates
+/// Enforced here rather than in the coordinator so write paths like import_data can't bypass it
+/// A new create is allowed once every earlier create has been deleted (e.g., after delete_account)
+fn validate_single_privacy_settings(action: &Action) -> ExternResult<ValidateCallbackResult> {
+    let Action::Create(create) = action else {
+        return Ok(ValidateCallbackResult::Valid);
+    };
+    if !is_privacy_settings_type(&create.entry_type)? {
+        return Ok(ValidateCallbackResult::Valid);
+    }
+
+    // Scan everything this agent authored before this action
+    let prior_activity = must_get_agent_activity(
+        create.author.clone(),
+        ChainFilter::new(create.prev_action.clone()),
+    )?;
+    let mut prior_creates = Vec::new();
+    let mut prior_deletes = Vec::new();
+    for prior in prior_activity {
+        match &prior.action.hashed.content {
+            Action::Create(prior_create) if is_privacy_settings_type(&prior_create.entry_type)? => {
+                prior_creates.push(prior.action.hashed.hash.clone());
+            }
+            Action::Delete(prior_delete) => prior_deletes.push(prior_delete.deletes_address.clone()),
+            _ => {}
+        }
+    }
+    if has_live_create(&prior_creates, &prior_deletes) {
+        return Ok(ValidateCallbackResult::Invalid(
+            "Agent already has PrivacySettings; update the existing entry instead".into()
+        ));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Whether any of the given creates is still live (not targeted by one of the deletes)
+fn has_live_create(creates: &[ActionHash], deletes: &[ActionHash]) -> bool {
+    creates.iter().any(|create_hash| !deletes.contains(create_hash))
+}
+
+/// Validate all operations on private DHT
+/// Membrane proof validation happens at genesis
+#[hdk_extern]
+pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
+    match op {
+        Op::StoreRecord(store_record) => {
+            // Validate that operations are from the correct agent
+            match store_record.record.action() {
+                Action::Create(_create) => {
+                    // Verify the entry content itself is consistent
+                    validate_record_entry(&store_record.record)
+                }
+                Action::Update(update) => {
+                    // Verify author matches original entry author
+                    let original_record = must_get_valid_record(update.original_action_address.clone())?;
+                    if *original_record.action().author() == update.author {
+                        match validate_record_entry(&store_record.record)? {
+                            ValidateCallbackResult::Valid => match store_record.record.entry().as_option() {
+                                Some(entry) => validate_update_entry(&original_record, &update.entry_type, entry),
+                                None => Ok(ValidateCallbackResult::Valid),
+                            },
+                            invalid => Ok(invalid),
+                        }
+                    } else {
+                        Ok(ValidateCallbackResult::Invalid(
+                            "Only original author can update".into()
+                        ))
+                    }
+                }
+                Action::Delete(delete) => {
+                    // Verify author matches original entry author
+                    let original_record = must_get_valid_record(delete.deletes_address.clone())?;
+                    if *original_record.action().author() == delete.author {
+                        Ok(ValidateCallbackResult::Valid)
+                    } else {
+                        Ok(ValidateCallbackResult::Invalid(
+                            "Only original author can delete".into()
+                        ))
+                    }
+                }
+                _ => Ok(ValidateCallbackResult::Valid)
+            }
+        }
+        Op::StoreEntry(_) => Ok(ValidateCallbackResult::Valid),
+        Op::RegisterUpdate(update) => {
+            // Verify update author matches original
+            let original_record = must_get_valid_record(update.update.hashed.content.original_action_address.clone())?;
+            if original_record.action().author() == &update.update.hashed.content.author {
+                // new_entry is None for private entries; StoreRecord covers those on the author's node
+                match &update.new_entry {
+                    Some(entry) => validate_update_entry(&original_record, &update.update.hashed.content.entry_type, entry),
+                    None => Ok(ValidateCallbackResult::Valid),
+                }
+            } else {
+                Ok(ValidateCallbackResult::Invalid(
+                    "Update author must match original author".into()
+                ))
+            }
+        }
+        Op::RegisterDelete(delete) => {
+            // Verify delete author matches original
+            let original_record = must_get_valid_record(delete.delete.hashed.content.deletes_address.clone())?;
+            if original_record.action().author() == &delete.delete.hashed.content.author {
+                Ok(ValidateCallbackResult::Valid)
+            } else {
+                Ok(ValidateCallbackResult::Invalid(
+                    "Delete author must match original author".into()
+                ))
+            }
+        }
+        Op::RegisterCreateLink(register) => validate_create_link(&register.create_link.hashed.content),
+        Op::RegisterDeleteLink(_) => Ok(ValidateCallbackResult::Valid),
+        Op::RegisterAgentActivity(activity) => validate_single_privacy_settings(&activity.action.hashed.content),
+    }
+}
+
+/// DNA properties set per deployment in dna.yaml (e.g., staging vs production)
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PrivateDnaProperties {
+    /// Base64 agent key (uhCAk...) whose signature over the joining agent's key is the membrane proof
+    #[serde(default)]
+    pub membrane_authority: Option<String>,
+}
+
+/// Membrane proof payload: the authority's signature over the joining agent's key
+#[derive(Serialize, Deserialize, Debug, SerializedBytes)]
+pub struct MembraneProofPayload {
+    pub signature: Signature,
+}
+
+/// Load DNA properties; None when dna.yaml sets no properties
+fn dna_properties() -> ExternResult<Option<PrivateDnaProperties>> {
+    let properties = dna_info()?.modifiers.properties;
+    holochain_serialized_bytes::decode::<_, Option<PrivateDnaProperties>>(properties.bytes())
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Invalid DNA properties: {:?}", e))))
+}
+
+/// Genesis self-check - validates membrane proof
+/// This is called when an agent tries to join the private DHT
+#[hdk_extern]
+pub fn genesis_self_check(data: GenesisSelfCheckData) -> ExternResult<ValidateCallbackResult> {
+    // The authority key comes from DNA properties so operators can rotate it without a rebuild
+    // No properties (or no membrane_authority) = permissive mode; membrane proof is then
+    // enforced at the conductor level only
+    let Some(authority) = dna_properties()?.and_then(|properties| properties.membrane_authority) else {
+        return Ok(ValidateCallbackResult::Valid);
+    };
+
+    let Ok(authority_key) = AgentPubKeyB64::from_b64_str(&authority) else {
+        return Ok(ValidateCallbackResult::Invalid("membrane_authority is not a valid agent key".into()));
+    };
+
+    check_membrane_proof(AgentPubKey::from(authority_key), data.membrane_proof, data.agent_key, verify_signature)
+}
+
+/// The joining agent's membrane proof must be the authority's signature over the agent's key
+/// `verify` checks a signature (verify_signature in the conductor)
+fn check_membrane_proof(
+    authority_key: AgentPubKey,
+    membrane_proof: Option<MembraneProof>,
+    agent_key: AgentPubKey,
+    verify: impl FnOnce(AgentPubKey, Signature, AgentPubKey) -> ExternResult<bool>,
+) -> ExternResult<ValidateCallbackResult> {
+    let Some(membrane_proof) = membrane_proof else {
+        return Ok(ValidateCallbackResult::Invalid("Membrane proof required".into()));
+    };
+
+    let Ok(payload) = MembraneProofPayload::try_from(SerializedBytes::clone(&membrane_proof)) else {
+        return Ok(ValidateCallbackResult::Invalid("Malformed membrane proof".into()));
+    };
+
+    if verify(authority_key, payload.signature, agent_key)? {
+        Ok(ValidateCallbackResult::Valid)
+    } else {
+        Ok(ValidateCallbackResult::Invalid("Membrane proof not signed by the membrane authority".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action_hash(byte: u8) -> ActionHash {
+        ActionHash::from_raw_36(vec![byte; 36])
+    }
+
+    fn profile(created_at: i64, updated_at: i64) -> UserProfile {
+        UserProfile {
+            encrypted_email: "ZW1haWw=".into(),
+            nonce: "bm9uY2U=".into(),
+            salt: "c2FsdA==".into(),
+            tag: "dGFn".into(),
+            username: None,
+            display_name: "Ada".into(),
+            created_at,
+            updated_at,
+        }
+    }
+
+    fn recovery_phrase(encrypted_mnemonic: &str, verified: bool, created_at: i64) -> RecoveryPhrase {
+        RecoveryPhrase {
+            encrypted_mnemonic: encrypted_mnemonic.into(),
+            nonce: "bm9uY2U=".into(),
+            salt: "c2FsdA==".into(),
+            tag: "dGFn".into(),
+            verified,
+            created_at,
+        }
+    }
+
+    fn privacy_settings(max_sessions: u32, default_page_size: u32) -> PrivacySettings {
+        PrivacySettings {
+            track_ip_address: true,
+            track_user_agent: true,
+            activity_log_retention_days: 90,
+            auto_anonymize_after_days: None,
+            default_page_size,
+            max_sessions,
+            track_dashboard_activity: true,
+            track_oauth_activity: true,
+            track_region: true,
+            created_at: 1,
+            updated_at: 1,
+        }
+    }
+
+    fn is_valid(result: ValidateCallbackResult) -> bool {
+        matches!(result, ValidateCallbackResult::Valid)
+    }
+
+    #[test]
+    fn entries_need_a_positive_created_at() {
+        assert!(is_valid(validate_entry(&EntryTypes::UserProfile(profile(1, 1)))));
+        assert!(!is_valid(validate_entry(&EntryTypes::UserProfile(profile(0, 0)))));
+        assert!(!is_valid(validate_entry(&EntryTypes::RecoveryPhrase(recovery_phrase("bW5lbW9uaWM=", false, -5)))));
+    }
+
+    #[test]
+    fn profile_updated_at_cannot_precede_created_at() {
+        assert!(is_valid(validate_user_profile(&profile(100, 100))));
+        assert!(is_valid(validate_user_profile(&profile(100, 200))));
+        assert!(!is_valid(validate_user_profile(&profile(100, 99))));
+    }
+
+    #[test]
+    fn profile_created_at_is_immutable() {
+        let original = EntryTypes::UserProfile(profile(100, 100));
+        assert!(is_valid(validate_immutable_fields(&original, &EntryTypes::UserProfile(profile(100, 300)))));
+        assert!(!is_valid(validate_immutable_fields(&original, &EntryTypes::UserProfile(profile(50, 300)))));
+    }
+
+    #[test]
+    fn recovery_phrase_created_at_only_changes_on_rotation_or_verification() {
+        let original = EntryTypes::RecoveryPhrase(recovery_phrase("b2xk", false, 100));
+        let restamped = EntryTypes::RecoveryPhrase(recovery_phrase("b2xk", false, 200));
+        let rotated = EntryTypes::RecoveryPhrase(recovery_phrase("bmV3", false, 200));
+        let verified = EntryTypes::RecoveryPhrase(recovery_phrase("b2xk", true, 200));
+        assert!(!is_valid(validate_immutable_fields(&original, &restamped)));
+        assert!(is_valid(validate_immutable_fields(&original, &rotated)));
+        assert!(is_valid(validate_immutable_fields(&original, &verified)));
+    }
+
+    #[test]
+    fn privacy_settings_need_at_least_one_session() {
+        assert!(is_valid(validate_entry(&EntryTypes::PrivacySettings(privacy_settings(1, 100)))));
+        assert!(!is_valid(validate_entry(&EntryTypes::PrivacySettings(privacy_settings(0, 100)))));
+    }
+
+    #[test]
+    fn privacy_settings_need_a_non_zero_page_size() {
+        assert!(is_valid(validate_entry(&EntryTypes::PrivacySettings(privacy_settings(10, 1)))));
+        assert!(!is_valid(validate_entry(&EntryTypes::PrivacySettings(privacy_settings(10, 0)))));
+    }
+
+    #[test]
+    fn first_privacy_settings_create_is_allowed() {
+        assert!(!has_live_create(&[], &[]));
+    }
+
+    #[test]
+    fn second_privacy_settings_create_is_rejected() {
+        assert!(has_live_create(&[action_hash(1)], &[]));
+        // Deleting some other record doesn't free the slot
+        assert!(has_live_create(&[action_hash(1)], &[action_hash(2)]));
+    }
+
+    #[test]
+    fn privacy_settings_can_be_recreated_after_delete() {
+        assert!(!has_live_create(&[action_hash(1)], &[action_hash(1)]));
+        assert!(has_live_create(&[action_hash(1), action_hash(2)], &[action_hash(1)]));
+    }
+
+    fn email_permission(granted: bool, granted_at: Option<i64>, revoked_at: Option<i64>) -> EmailPermission {
+        EmailPermission {
+            service_name: "billing".into(),
+            purpose: "Invoices".into(),
+            granted,
+            granted_at,
+            revoked_at,
+            last_used_at: None,
+            expires_at: None,
+            created_at: 1,
+            updated_at: 1,
+            category: PermissionCategory::Other,
+        }
+    }
+
+    #[test]
+    fn granted_permission_cannot_be_revoked() {
+        assert!(is_valid(validate_email_permission(&email_permission(true, Some(1), None))));
+        assert!(!is_valid(validate_email_permission(&email_permission(true, Some(1), Some(2)))));
+    }
+
+    #[test]
+    fn withdrawn_permission_needs_revoked_at() {
+        assert!(is_valid(validate_email_permission(&email_permission(false, Some(1), Some(2)))));
+        assert!(!is_valid(validate_email_permission(&email_permission(false, Some(1), None))));
+        // Never granted, so there is nothing to revoke
+        assert!(is_valid(validate_email_permission(&email_permission(false, None, None))));
+    }
+
+    fn session(created_at: i64, last_active: i64) -> Session {
+        Session {
+            user_agent: "Mozilla/5.0".into(),
+            ip_address: "203.0.113.7".into(),
+            device_info: "laptop".into(),
+            conductor_id: "edge-1".into(),
+            created_at,
+            last_active,
+            is_current: false,
+        }
+    }
+
+    #[test]
+    fn session_last_active_cannot_precede_created_at() {
+        assert!(is_valid(validate_entry(&EntryTypes::Session(session(100, 100)))));
+        assert!(is_valid(validate_entry(&EntryTypes::Session(session(100, 250)))));
+        assert!(!is_valid(validate_entry(&EntryTypes::Session(session(100, 99)))));
+    }
+
+    fn oauth_activity(app_id: &str) -> OAuthActivity {
+        OAuthActivity {
+            timestamp: 1,
+            app_id: app_id.into(),
+            app_name: "Example".into(),
+            event_type: "login".into(),
+            created_at: 1,
+            seq: None,
+            scopes: vec![],
+        }
+    }
+
+    #[test]
+    fn oauth_app_id_is_bounded() {
+        assert!(is_valid(validate_oauth_activity(&oauth_activity(&"a".repeat(MAX_APP_ID_LEN)))));
+        assert!(!is_valid(validate_oauth_activity(&oauth_activity(&"a".repeat(MAX_APP_ID_LEN + 1)))));
+        assert!(!is_valid(validate_oauth_activity(&oauth_activity(""))));
+    }
+
+    #[test]
+    fn oauth_app_id_only_allows_identifier_characters() {
+        assert!(is_valid(validate_oauth_activity(&oauth_activity("com.example:app-v2_beta"))));
+        assert!(!is_valid(validate_oauth_activity(&oauth_activity("my app"))));
+        assert!(!is_valid(validate_oauth_activity(&oauth_activity("app/../id"))));
+        assert!(!is_valid(validate_oauth_activity(&oauth_activity("appé"))));
+    }
+
+    fn agent_key(byte: u8) -> AgentPubKey {
+        AgentPubKey::from_raw_36(vec![byte; 36])
+    }
+
+    fn create_link(author: AgentPubKey, base: AnyLinkableHash, target: AnyLinkableHash) -> CreateLink {
+        CreateLink {
+            author,
+            timestamp: Timestamp::from_micros(1),
+            action_seq: 4,
+            prev_action: action_hash(3),
+            base_address: base,
+            target_address: target,
+            zome_index: 0.into(),
+            link_type: 0.into(),
+            tag: LinkTag::new(vec![]),
+            weight: Default::default(),
+        }
+    }
+
+    #[test]
+    fn link_from_own_key_to_an_action_passes_the_structural_checks() {
+        let link = create_link(agent_key(1), agent_key(1).into(), action_hash(7).into());
+        assert_eq!(link_target_action(&link).ok(), Some(action_hash(7)));
+    }
+
+    #[test]
+    fn link_based_on_another_agent_is_rejected() {
+        let link = create_link(agent_key(1), agent_key(2).into(), action_hash(7).into());
+        assert!(link_target_action(&link).is_err());
+    }
+
+    #[test]
+    fn link_to_an_entry_hash_is_rejected() {
+        let target = EntryHash::from_raw_36(vec![7; 36]);
+        let link = create_link(agent_key(1), agent_key(1).into(), target.into());
+        assert!(link_target_action(&link).is_err());
+    }
+
+    #[test]
+    fn link_target_must_be_the_expected_app_entry_type() {
+        let expected = ScopedEntryDefIndex { zome_index: 0.into(), zome_type: 3.into() };
+        let app_entry = |zome_index: u8, entry_index: u8| {
+            EntryType::App(AppEntryDef::new(entry_index.into(), zome_index.into(), EntryVisibility::Private))
+        };
+        assert!(entry_type_matches(&app_entry(0, 3), &expected));
+        assert!(!entry_type_matches(&app_entry(0, 4), &expected));
+        assert!(!entry_type_matches(&app_entry(1, 3), &expected));
+        assert!(!entry_type_matches(&EntryType::AgentPubKey, &expected));
+    }
+
+    #[test]
+    fn link_types_target_their_entry_types() {
+        assert!(matches!(link_target_type(&LinkTypes::AgentToProfile), UnitEntryTypes::UserProfile));
+        assert!(matches!(link_target_type(&LinkTypes::AgentToSessions), UnitEntryTypes::Session));
+        assert!(matches!(link_target_type(&LinkTypes::AgentToPrivacySettings), UnitEntryTypes::PrivacySettings));
+        assert!(matches!(link_target_type(&LinkTypes::AgentToImportCheckpoint), UnitEntryTypes::ImportCheckpoint));
+    }
+
+    /// Stand-in for verify_signature: only the authority (key 9) signing with [7; 64] verifies
+    fn verify(key: AgentPubKey, signature: Signature, _agent_key: AgentPubKey) -> ExternResult<bool> {
+        Ok(key == agent_key(9) && signature == Signature([7; 64]))
+    }
+
+    fn membrane_proof(signature: Signature) -> MembraneProof {
+        let payload = MembraneProofPayload { signature };
+        std::sync::Arc::new(SerializedBytes::try_from(payload).unwrap())
+    }
+
+    #[test]
+    fn membrane_proof_signed_by_the_authority_is_accepted() {
+        let proof = Some(membrane_proof(Signature([7; 64])));
+        assert!(is_valid(check_membrane_proof(agent_key(9), proof, agent_key(1), verify).unwrap()));
+    }
+
+    #[test]
+    fn membrane_proof_signed_by_anyone_else_is_rejected() {
+        let proof = Some(membrane_proof(Signature([8; 64])));
+        assert!(!is_valid(check_membrane_proof(agent_key(9), proof, agent_key(1), verify).unwrap()));
+    }
+
+    #[test]
+    fn missing_or_malformed_membrane_proof_is_rejected() {
+        assert!(!is_valid(check_membrane_proof(agent_key(9), None, agent_key(1), verify).unwrap()));
+        let garbage = std::sync::Arc::new(SerializedBytes::from(UnsafeBytes::from(vec![0xc1])));
+        assert!(!is_valid(check_membrane_proof(agent_key(9), Some(garbage), agent_key(1), verify).unwrap()));
+    }
+}